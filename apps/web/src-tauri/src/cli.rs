@@ -0,0 +1,677 @@
+//! Headless command-line mode: `vimix process`, `vimix batch`,
+//! `vimix pipeline` and `vimix list-processors` run the bundled sidecar
+//! without opening a window. `vimix serve` keeps it running as a service,
+//! see `serve`.
+//!
+//! Progress goes to stderr and the paths of saved results to stdout, one
+//! per line, so scripts can capture them. Exit codes:
+//!
+//! - `0`: every job succeeded
+//! - `1`: at least one job failed
+//! - `2`: invalid arguments
+//! - `3`: the processing engine could not be started
+//! - `130`: interrupted
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use hyper::Method;
+use serde_json::{Map, Value};
+
+use crate::headless::{HeadlessOptions, HeadlessSidecar};
+use crate::inputs;
+use crate::jobs::{self, JobInfo, Submitted};
+use crate::pipelines::{self, Engine, Pipeline, PipelineStore, StepStatus};
+use crate::processors::Processor;
+use crate::results::{self, OnConflict, SaveMode, SaveOptions, DEFAULT_TEMPLATE};
+use crate::serve::{self, ServeArgs};
+use crate::sidecar::ApiToken;
+
+pub const EXIT_FAILED: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_BACKEND: i32 = 3;
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// Exit code, or an exit code and the error to print.
+type CliResult = Result<i32, (i32, String)>;
+
+/// Arguments that select command-line mode when they come first, after
+/// any flags. Anything else (no arguments, files from "Open with",
+/// `vimix://` links) opens the app.
+const SUBCOMMANDS: [&str; 6] = [
+    "process",
+    "batch",
+    "pipeline",
+    "list-processors",
+    "serve",
+    "help",
+];
+
+/// The app's `identifier` in `tauri.conf.json`; its config and cache dirs
+/// are named after it.
+pub const APP_IDENTIFIER: &str = "com.vimix.app";
+
+#[derive(Parser)]
+#[command(
+    name = "vimix",
+    version,
+    about = "Process media with Vimix without opening the app"
+)]
+struct Cli {
+    /// Show the processing engine's log output.
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Run a processor on the given files.
+    ///
+    /// Processors that combine files (e.g. pdf-merge) make one job of
+    /// them; others process each file separately.
+    Process {
+        #[command(flatten)]
+        job: JobArgs,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Run a processor on every accepted file among the given files and
+    /// folders, one job per file. Failed files don't stop the others.
+    Batch {
+        #[command(flatten)]
+        job: JobArgs,
+        #[arg(required = true, value_name = "FILES_OR_FOLDERS")]
+        inputs: Vec<PathBuf>,
+    },
+    /// Run files through a pipeline of processors, or list the pipelines
+    /// saved in the app.
+    #[command(subcommand)]
+    Pipeline(PipelineCommand),
+    /// List the available processors.
+    ListProcessors {
+        /// Print the full descriptions as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run the processing API as a long-lived local service until
+    /// SIGTERM or Ctrl+C.
+    Serve(ServeArgs),
+}
+
+#[derive(Subcommand)]
+enum PipelineCommand {
+    /// List the pipelines saved in the app.
+    List,
+    /// Run each file through every step of a pipeline and save the final
+    /// result. A failed step stops that file only.
+    #[command(group(ArgGroup::new("source").required(true).args(["pipeline", "file"])))]
+    Run {
+        /// Name or id of a pipeline saved in the app.
+        #[arg(short, long)]
+        pipeline: Option<String>,
+        /// Pipeline JSON file with a `name` and `steps`, each a
+        /// `processor_id` and its `options`.
+        #[arg(short, long, value_name = "FILE")]
+        file: Option<PathBuf>,
+        #[command(flatten)]
+        save: SaveArgs,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+struct SaveArgs {
+    /// Folder the results are saved into; created if missing.
+    #[arg(short, long, default_value = ".")]
+    output: PathBuf,
+    /// Result file name; placeholders: {stem}, {name}, {ext}, {processor},
+    /// {job} and {index}.
+    #[arg(long, default_value = DEFAULT_TEMPLATE)]
+    name: String,
+    /// What to do when a result file already exists.
+    #[arg(long, value_enum, default_value = "rename")]
+    on_conflict: OnConflict,
+}
+
+impl SaveArgs {
+    fn save_options(&self) -> SaveOptions {
+        SaveOptions {
+            template: Some(self.name.clone()),
+            on_conflict: self.on_conflict,
+            // The data dir is thrown away afterwards anyway.
+            mode: SaveMode::Move,
+        }
+    }
+}
+
+#[derive(Args)]
+struct JobArgs {
+    /// Processor id, see `vimix list-processors`.
+    #[arg(short, long)]
+    processor: String,
+    /// Processor option; repeat for several.
+    #[arg(long = "opt", value_name = "KEY=VALUE", value_parser = parse_option)]
+    options: Vec<(String, String)>,
+    #[command(flatten)]
+    save: SaveArgs,
+}
+
+fn parse_option(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got {arg}"))
+}
+
+/// Run the command line given in `args` if it selects a subcommand.
+///
+/// Returns the process exit code, or `None` when the app should start
+/// normally instead.
+pub fn run(args: Vec<OsString>) -> Option<i32> {
+    if !is_command_line(&args) {
+        return None;
+    }
+    attach_console();
+    let cli = match Cli::try_parse_from(args) {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return Some(if e.use_stderr() { EXIT_USAGE } else { 0 });
+        }
+    };
+    Some(tauri::async_runtime::block_on(execute(cli)))
+}
+
+/// Whether `args` ask for command-line mode: `--help`, `--version`, or a
+/// subcommand as the first argument that isn't a flag (e.g. `-v process`).
+fn is_command_line(args: &[OsString]) -> bool {
+    args.iter()
+        .skip(1)
+        .find_map(|arg| match arg.to_string_lossy().as_ref() {
+            "-h" | "--help" | "-V" | "--version" => Some(true),
+            flag if flag.starts_with('-') => None,
+            first => Some(SUBCOMMANDS.contains(&first)),
+        })
+        .unwrap_or(false)
+}
+
+async fn execute(cli: Cli) -> i32 {
+    let command = match cli.command {
+        CliCommand::Serve(args) => {
+            return serve::run(args).await.unwrap_or_else(|reason| {
+                eprintln!("error: {reason}");
+                EXIT_FAILED
+            })
+        }
+        // Only reads the app's config, no sidecar needed
+        CliCommand::Pipeline(PipelineCommand::List) => return list_pipelines(),
+        command => command,
+    };
+
+    // A private data dir per run, so concurrent runs and the app don't
+    // see each other's jobs.
+    let data_dir = std::env::temp_dir().join(format!("vimix-cli-{}", std::process::id()));
+    let options = HeadlessOptions {
+        host: "127.0.0.1".to_string(),
+        port: 0,
+        data_dir: data_dir.clone(),
+        token: ApiToken::generate().0,
+        verbose: cli.verbose,
+    };
+    let sidecar = match HeadlessSidecar::start(&options).await {
+        Ok(sidecar) => sidecar,
+        Err(reason) => {
+            eprintln!("error: {reason}");
+            let _ = std::fs::remove_dir_all(&data_dir);
+            return EXIT_BACKEND;
+        }
+    };
+
+    let code = tokio::select! {
+        code = dispatch(&sidecar, command) => code,
+        _ = tokio::signal::ctrl_c() => {
+            eprintln!("Interrupted");
+            EXIT_INTERRUPTED
+        }
+    };
+    sidecar.shutdown().await;
+    let _ = std::fs::remove_dir_all(&data_dir);
+    code
+}
+
+async fn dispatch(sidecar: &HeadlessSidecar, command: CliCommand) -> i32 {
+    let result = match command {
+        CliCommand::Process { job, files } => process(sidecar, job, files).await,
+        CliCommand::Batch { job, inputs } => batch(sidecar, job, inputs).await,
+        CliCommand::Pipeline(PipelineCommand::Run {
+            pipeline,
+            file,
+            save,
+            files,
+        }) => run_pipeline(sidecar, pipeline, file, save, files).await,
+        CliCommand::ListProcessors { json } => list_processors(sidecar, json).await,
+        CliCommand::Serve(_) | CliCommand::Pipeline(PipelineCommand::List) => {
+            unreachable!("handled without a sidecar")
+        }
+    };
+    result.unwrap_or_else(|(code, reason)| {
+        eprintln!("error: {reason}");
+        code
+    })
+}
+
+async fn list_processors(sidecar: &HeadlessSidecar, json: bool) -> CliResult {
+    let raw: Value = sidecar
+        .call(Method::GET, "/processors", None)
+        .await
+        .map_err(|e| (EXIT_FAILED, e))?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&raw).unwrap_or_default());
+        return Ok(0);
+    }
+    let processors: Vec<Processor> =
+        serde_json::from_value(raw).map_err(|e| (EXIT_FAILED, e.to_string()))?;
+    let width = processors.iter().map(|p| p.id.len()).max().unwrap_or(0);
+    for p in processors {
+        println!(
+            "{:width$}  {} ({})",
+            p.id,
+            p.label,
+            p.accepted_extensions.join(", ")
+        );
+    }
+    Ok(0)
+}
+
+async fn process(sidecar: &HeadlessSidecar, args: JobArgs, files: Vec<PathBuf>) -> CliResult {
+    let (processor, options) = prepare(sidecar, &args).await?;
+    if let Some(file) = files.iter().find(|file| !processor.accepts(file)) {
+        return Err((
+            EXIT_USAGE,
+            format!("{} doesn't accept {}", processor.id, file.display()),
+        ));
+    }
+    let job_ids = match submit(sidecar, &processor.id, files, &options).await? {
+        Submitted::Job { id } => vec![id],
+        Submitted::Batch { job_ids, .. } => job_ids,
+    };
+    follow(sidecar, &args.save, job_ids).await
+}
+
+async fn batch(sidecar: &HeadlessSidecar, args: JobArgs, paths: Vec<PathBuf>) -> CliResult {
+    let (processor, options) = prepare(sidecar, &args).await?;
+    let (files, skipped): (Vec<_>, Vec<_>) = inputs::resolve(paths)
+        .into_iter()
+        .partition(|file| processor.accepts(file));
+    for file in &skipped {
+        eprintln!(
+            "Skipping {}: not accepted by {}",
+            file.display(),
+            processor.id
+        );
+    }
+    if files.is_empty() {
+        return Err((EXIT_USAGE, "No files to process".to_string()));
+    }
+
+    // One request per file, so multi-file processors run on each one alone.
+    let mut job_ids = Vec::with_capacity(files.len());
+    for file in files {
+        match submit(sidecar, &processor.id, vec![file], &options).await? {
+            Submitted::Job { id } => job_ids.push(id),
+            Submitted::Batch { job_ids: ids, .. } => job_ids.extend(ids),
+        }
+    }
+    follow(sidecar, &args.save, job_ids).await
+}
+
+/// Look up the processor and check the `--opt` values against its schema.
+async fn prepare(
+    sidecar: &HeadlessSidecar,
+    args: &JobArgs,
+) -> Result<(Processor, Map<String, Value>), (i32, String)> {
+    let processors: Vec<Processor> = sidecar
+        .call(Method::GET, "/processors", None)
+        .await
+        .map_err(|e| (EXIT_FAILED, e))?;
+    let processor = processors
+        .into_iter()
+        .find(|p| p.id == args.processor)
+        .ok_or_else(|| (EXIT_USAGE, format!("Unknown processor {}", args.processor)))?;
+
+    let mut options = Map::new();
+    for (id, raw) in &args.options {
+        let value = processor
+            .option_value(id, raw)
+            .map_err(|e| (EXIT_USAGE, e))?;
+        options.insert(id.clone(), value);
+    }
+    create_output(&args.save)?;
+    Ok((processor, options))
+}
+
+fn create_output(args: &SaveArgs) -> Result<(), (i32, String)> {
+    std::fs::create_dir_all(&args.output).map_err(|e| {
+        (
+            EXIT_FAILED,
+            format!("Failed to create {}: {e}", args.output.display()),
+        )
+    })
+}
+
+async fn submit(
+    sidecar: &HeadlessSidecar,
+    processor_id: &str,
+    files: Vec<PathBuf>,
+    options: &Map<String, Value>,
+) -> Result<Submitted, (i32, String)> {
+    let mut paths = Vec::with_capacity(files.len());
+    for file in files {
+        let path = file
+            .canonicalize()
+            .map_err(|e| (EXIT_USAGE, format!("{}: {e}", file.display())))?;
+        paths.push(path);
+    }
+    let body = serde_json::json!({
+        "processor_id": processor_id,
+        "paths": paths,
+        "options": options,
+    });
+    sidecar
+        .call(Method::POST, "/jobs/local", Some(&body))
+        .await
+        .map_err(|e| (EXIT_FAILED, e))
+}
+
+/// Print progress until every job has finished and save the results.
+///
+/// Jobs are waited for in order, so results are printed in the order of
+/// the inputs; the sidecar still runs them side by side.
+async fn follow(sidecar: &HeadlessSidecar, args: &SaveArgs, job_ids: Vec<String>) -> CliResult {
+    let save_options = args.save_options();
+    let jobs_dir = sidecar.jobs_dir();
+    let engine = Engine::Headless(sidecar);
+    let total = job_ids.len();
+    let prefix = |index: usize, job: &JobInfo| {
+        if total > 1 {
+            format!("[{}/{total}] {}", index + 1, job.original_filename)
+        } else {
+            job.original_filename.clone()
+        }
+    };
+    let mut failed = 0;
+
+    for (index, id) in job_ids.iter().enumerate() {
+        let mut shown = None;
+        let job = engine
+            .wait_for_job(id, |job| {
+                // Report every 10% step or new message, not every poll.
+                let step = (job.progress as u32 / 10, job.message.clone());
+                if shown.as_ref() != Some(&step) {
+                    eprintln!(
+                        "{}: {:>3}% {}",
+                        prefix(index, job),
+                        job.progress as u32,
+                        job.message
+                    );
+                    shown = Some(step);
+                }
+            })
+            .await
+            .map_err(|e| (EXIT_FAILED, e))?;
+        let prefix = prefix(index, &job);
+        if job.status == "failed" {
+            let reason = job.error.as_deref().unwrap_or(&job.status);
+            eprintln!("{prefix}: failed: {reason}");
+            failed += 1;
+            continue;
+        }
+        match save(&jobs_dir, &job, &args.output, &save_options, index + 1) {
+            Ok(Some(path)) => {
+                eprintln!("{prefix}: done");
+                println!("{}", path.display());
+            }
+            Ok(None) => eprintln!("{prefix}: done, result not saved (already exists)"),
+            Err(reason) => {
+                eprintln!("{prefix}: {reason}");
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        eprintln!("{failed} of {total} jobs failed");
+        return Ok(EXIT_FAILED);
+    }
+    Ok(0)
+}
+
+/// Run every file through the pipeline, one after the other, printing
+/// progress per step and which step failed and why.
+async fn run_pipeline(
+    sidecar: &HeadlessSidecar,
+    name: Option<String>,
+    file: Option<PathBuf>,
+    args: SaveArgs,
+    files: Vec<PathBuf>,
+) -> CliResult {
+    let pipeline = match (name, file) {
+        (_, Some(file)) => read_pipeline(&file)?,
+        (Some(name), None) => PipelineStore::read(&app_config_dir()?)
+            .map_err(|e| (EXIT_FAILED, e))?
+            .find(&name)
+            .ok_or_else(|| {
+                (
+                    EXIT_USAGE,
+                    format!("No pipeline {name}, see `vimix pipeline list`"),
+                )
+            })?,
+        (None, None) => unreachable!("clap requires --pipeline or --file"),
+    };
+    let engine = Engine::Headless(sidecar);
+    let pipeline = pipelines::check(engine, pipeline)
+        .await
+        .map_err(|e| (EXIT_USAGE, e))?;
+    let processors: Vec<Processor> = sidecar
+        .call(Method::GET, "/processors", None)
+        .await
+        .map_err(|e| (EXIT_FAILED, e))?;
+    let first = processors
+        .iter()
+        .find(|p| p.id == pipeline.steps[0].processor_id)
+        .ok_or_else(|| (EXIT_FAILED, "Processors changed".to_string()))?;
+    if let Some(file) = files.iter().find(|file| !first.accepts(file)) {
+        return Err((
+            EXIT_USAGE,
+            format!("{} doesn't accept {}", first.id, file.display()),
+        ));
+    }
+    create_output(&args)?;
+
+    let save_options = args.save_options();
+    let total = files.len();
+    let steps = pipeline.steps.len();
+    let mut failed = 0;
+    for (index, file) in files.iter().enumerate() {
+        let filename = file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| file.display().to_string());
+        let prefix = if total > 1 {
+            format!("[{}/{total}] {filename}", index + 1)
+        } else {
+            filename
+        };
+        let mut shown = None;
+        let run = pipelines::run(
+            engine,
+            &pipeline,
+            file,
+            &args.output,
+            &save_options,
+            Some(index + 1),
+            |run| {
+                let Some(step) = run
+                    .steps
+                    .iter()
+                    .position(|s| s.status == StepStatus::Running)
+                else {
+                    return;
+                };
+                let report = &run.steps[step];
+                // Report every 10% step or new message, not every poll.
+                let now = (step, report.progress as u32 / 10, report.message.clone());
+                if shown.as_ref() != Some(&now) {
+                    eprintln!(
+                        "{prefix}: step {}/{steps} {}: {:>3}% {}",
+                        step + 1,
+                        report.processor_id,
+                        report.progress as u32,
+                        report.message
+                    );
+                    shown = Some(now);
+                }
+            },
+        )
+        .await;
+
+        if let Some(path) = &run.output {
+            eprintln!("{prefix}: done");
+            println!("{}", path.display());
+            continue;
+        }
+        failed += 1;
+        match run
+            .steps
+            .iter()
+            .enumerate()
+            .find(|(_, step)| step.status == StepStatus::Failed)
+        {
+            Some((step, report)) => eprintln!(
+                "{prefix}: step {}/{steps} {} failed: {}",
+                step + 1,
+                report.processor_id,
+                report.error.as_deref().unwrap_or("unknown error")
+            ),
+            None => eprintln!(
+                "{prefix}: result not saved: {}",
+                run.error.as_deref().unwrap_or("unknown error")
+            ),
+        }
+    }
+
+    if failed > 0 {
+        eprintln!("{failed} of {total} files failed");
+        return Ok(EXIT_FAILED);
+    }
+    Ok(0)
+}
+
+fn list_pipelines() -> i32 {
+    let dir = match app_config_dir() {
+        Ok(dir) => dir,
+        Err((code, reason)) => {
+            eprintln!("error: {reason}");
+            return code;
+        }
+    };
+    let pipelines = match PipelineStore::read(&dir) {
+        Ok(store) => store.list(),
+        Err(reason) => {
+            eprintln!("error: {reason}");
+            return EXIT_FAILED;
+        }
+    };
+    let width = pipelines.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    for pipeline in pipelines {
+        let steps: Vec<&str> = pipeline
+            .steps
+            .iter()
+            .map(|step| step.processor_id.as_str())
+            .collect();
+        println!("{:width$}  {}", pipeline.name, steps.join(" -> "));
+    }
+    0
+}
+
+fn read_pipeline(path: &Path) -> Result<Pipeline, (i32, String)> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        (
+            EXIT_USAGE,
+            format!("Failed to read {}: {e}", path.display()),
+        )
+    })?;
+    serde_json::from_str(&text).map_err(|e| {
+        (
+            EXIT_USAGE,
+            format!("Invalid pipeline {}: {e}", path.display()),
+        )
+    })
+}
+
+/// Where the app keeps its settings, presets and pipelines; the same dir
+/// as Tauri's `app_config_dir`.
+fn app_config_dir() -> Result<PathBuf, (i32, String)> {
+    dirs::config_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| (EXIT_FAILED, "No config directory".to_string()))
+}
+
+fn save(
+    jobs_dir: &Path,
+    job: &JobInfo,
+    dest_dir: &Path,
+    options: &SaveOptions,
+    index: usize,
+) -> Result<Option<PathBuf>, String> {
+    let source = jobs::result_in(jobs_dir, job)?;
+    let saved = results::save(job, &source, dest_dir, options, Some(index))?;
+    Ok(saved.path)
+}
+
+/// Release builds on Windows are GUI programs without a console; borrow
+/// the one of the terminal we were started from so output shows up.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // SAFETY: AttachConsole has no memory-safety preconditions.
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        std::iter::once("vimix")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect()
+    }
+
+    #[test]
+    fn subcommands_after_flags_select_the_command_line() {
+        assert!(is_command_line(&args(&["process", "-p", "rembg", "a.png"])));
+        assert!(is_command_line(&args(&["-v", "batch", "photos"])));
+        assert!(is_command_line(&args(&["--verbose", "list-processors"])));
+        assert!(is_command_line(&args(&["--verbose", "pipeline", "list"])));
+        assert!(is_command_line(&args(&["--help"])));
+        assert!(is_command_line(&args(&["-V"])));
+        assert!(is_command_line(&args(&["help", "batch"])));
+    }
+
+    #[test]
+    fn everything_else_opens_the_app() {
+        assert!(!is_command_line(&args(&[])));
+        assert!(!is_command_line(&args(&["/home/me/photo.png"])));
+        assert!(!is_command_line(&args(&["vimix://open?file=a.png"])));
+        assert!(!is_command_line(&args(&["-v"])));
+        assert!(!is_command_line(&args(&["-v", "/home/me/process"])));
+    }
+}
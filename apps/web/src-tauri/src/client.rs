@@ -0,0 +1,150 @@
+//! Minimal HTTP/1.1 client for the sidecar API, over TCP or a Unix domain socket.
+
+use std::fmt;
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::header::{HeaderValue, CONTENT_TYPE, HOST};
+use hyper::{Method, Request, Response};
+use hyper_util::rt::TokioIo;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::sidecar::TOKEN_HEADER;
+
+/// How the sidecar exposes its API.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// A localhost TCP port, reachable directly from the webview.
+    #[default]
+    Tcp,
+    /// A Unix domain socket only the current user can open. The webview
+    /// reaches it through the `vimix-api://` proxy; no port is exposed.
+    Unix,
+}
+
+/// Where a running sidecar can be reached.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(u16),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(port) => write!(f, "127.0.0.1:{port}"),
+            #[cfg(unix)]
+            Self::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl Endpoint {
+    pub fn port(&self) -> Option<u16> {
+        match self {
+            Self::Tcp(port) => Some(*port),
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+        }
+    }
+
+    fn host(&self) -> String {
+        match self {
+            Self::Tcp(port) => format!("127.0.0.1:{port}"),
+            #[cfg(unix)]
+            Self::Unix(_) => "localhost".to_string(),
+        }
+    }
+}
+
+/// Send one request to the sidecar on a fresh connection.
+///
+/// `req` must use an origin-form URI (`/jobs?x=1`); the `Host` header is
+/// filled in here. The response body is returned unread so callers can
+/// either collect it or stream it.
+pub async fn send(
+    endpoint: &Endpoint,
+    mut req: Request<Full<Bytes>>,
+) -> Result<Response<Incoming>, String> {
+    let host = HeaderValue::from_str(&endpoint.host()).map_err(|e| e.to_string())?;
+    req.headers_mut().insert(HOST, host);
+
+    match endpoint {
+        Endpoint::Tcp(port) => {
+            let stream = tokio::net::TcpStream::connect(("127.0.0.1", *port))
+                .await
+                .map_err(|e| format!("Failed to connect to sidecar: {e}"))?;
+            send_over(stream, req).await
+        }
+        #[cfg(unix)]
+        Endpoint::Unix(path) => {
+            let stream = tokio::net::UnixStream::connect(path)
+                .await
+                .map_err(|e| format!("Failed to connect to sidecar socket: {e}"))?;
+            send_over(stream, req).await
+        }
+    }
+}
+
+/// Send a JSON request carrying the API `token` and decode the JSON response.
+///
+/// When the sidecar rejects the request, the error is FastAPI's `detail`.
+pub async fn call<T: DeserializeOwned>(
+    endpoint: &Endpoint,
+    token: &str,
+    method: Method,
+    path: &str,
+    body: Option<&serde_json::Value>,
+) -> Result<T, String> {
+    let mut req = Request::builder()
+        .method(method)
+        .uri(path)
+        .header(TOKEN_HEADER, token);
+    let body = match body {
+        Some(json) => {
+            req = req.header(CONTENT_TYPE, "application/json");
+            Bytes::from(json.to_string())
+        }
+        None => Bytes::new(),
+    };
+    let req = req.body(Full::new(body)).map_err(|e| e.to_string())?;
+
+    let response = send(endpoint, req).await?;
+    let status = response.status();
+    let bytes = response
+        .into_body()
+        .collect()
+        .await
+        .map_err(|e| format!("Failed to read sidecar response: {e}"))?
+        .to_bytes();
+    if !status.is_success() {
+        let detail = serde_json::from_slice::<serde_json::Value>(&bytes)
+            .ok()
+            .and_then(|json| json.get("detail")?.as_str().map(str::to_string));
+        return Err(detail.unwrap_or_else(|| format!("Sidecar returned {status}")));
+    }
+    serde_json::from_slice(&bytes).map_err(|e| format!("Invalid sidecar response: {e}"))
+}
+
+async fn send_over<S>(stream: S, req: Request<Full<Bytes>>) -> Result<Response<Incoming>, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(|e| format!("Sidecar handshake failed: {e}"))?;
+    tauri::async_runtime::spawn(async move {
+        let _ = conn.await;
+    });
+    sender
+        .send_request(req)
+        .await
+        .map_err(|e| format!("Sidecar request failed: {e}"))
+}
@@ -0,0 +1,107 @@
+//! Everything a sidecar launch depends on, gathered in one value.
+//!
+//! The config is applied to the sidecar `Command` only, never to the app's
+//! own environment, so concurrent launches and other child processes
+//! don't see each other's settings.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::toolchain::{Tool, ToolInfo};
+
+/// Arguments, environment and directories for one sidecar launch.
+#[derive(Clone, Debug, Default)]
+pub struct SidecarConfig {
+    /// External tools the processors shell out to. Tools missing here are
+    /// left for the Python `binary_paths` module to look up itself.
+    pub binaries: BTreeMap<&'static str, PathBuf>,
+    /// rembg model directory (`U2NET_HOME`), from the settings. `None`
+    /// keeps the models bundled with the sidecar.
+    pub model_dir: Option<PathBuf>,
+    /// Working directory of the sidecar process.
+    pub working_dir: Option<PathBuf>,
+    /// Where the sidecar keeps `uploads/` and `jobs/` (`VIMIX_DATA_DIR`).
+    /// `None` keeps its default next to the Python sources.
+    pub data_dir: Option<PathBuf>,
+    /// Command-line arguments, in order.
+    pub args: Vec<String>,
+    /// Extra environment variables, on top of the inherited environment.
+    pub env: BTreeMap<String, String>,
+}
+
+impl SidecarConfig {
+    /// Config using every available tool from a toolchain preflight.
+    pub fn new(tools: &[ToolInfo]) -> Self {
+        let binaries = tools
+            .iter()
+            .filter(|info| info.available)
+            .filter_map(|info| Some((info.tool.name(), info.path.clone()?)))
+            .collect();
+        Self {
+            binaries,
+            ..Self::default()
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// The full set of variables to pass to the sidecar: `env` plus the
+    /// tool paths and model and data directories under the names Python reads.
+    pub fn envs(&self) -> BTreeMap<String, String> {
+        let mut envs = self.env.clone();
+        for tool in Tool::ALL {
+            if let Some(path) = self.binaries.get(tool.name()) {
+                envs.insert(
+                    tool.env_var().to_string(),
+                    path.to_string_lossy().into_owned(),
+                );
+            }
+        }
+        if let Some(dir) = &self.model_dir {
+            envs.insert("U2NET_HOME".to_string(), dir.to_string_lossy().into_owned());
+        }
+        if let Some(dir) = &self.data_dir {
+            envs.insert(
+                "VIMIX_DATA_DIR".to_string(),
+                dir.to_string_lossy().into_owned(),
+            );
+        }
+        envs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envs_carry_tools_and_dirs_under_python_names() {
+        let mut config = SidecarConfig::default().env("PYTHONUNBUFFERED", "1");
+        config
+            .binaries
+            .insert(Tool::Ffmpeg.name(), PathBuf::from("/opt/ffmpeg/bin/ffmpeg"));
+        config.model_dir = Some(PathBuf::from("/models"));
+        config.data_dir = Some(PathBuf::from("/data"));
+
+        let envs = config.envs();
+        assert_eq!(envs["FFMPEG_BIN"], "/opt/ffmpeg/bin/ffmpeg");
+        assert_eq!(envs["U2NET_HOME"], "/models");
+        assert_eq!(envs["VIMIX_DATA_DIR"], "/data");
+        assert_eq!(envs["PYTHONUNBUFFERED"], "1");
+        assert_eq!(envs.len(), 4);
+    }
+
+    #[test]
+    fn envs_leave_out_what_is_not_set() {
+        let envs = SidecarConfig::default().envs();
+        assert!(envs.is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+//! `vimix://` deep links that let other apps and scripts start a job:
+//!
+//! ```text
+//! vimix://process?processor=pdf-merge&file=/a.pdf&file=/b.pdf&opt.quality=80
+//! ```
+//!
+//! Links are checked against the processor's options schema, then held
+//! until the user confirms them; a link alone never starts anything.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager, Url};
+
+use crate::jobs::{self, Submitted};
+use crate::processors::{self, Processor};
+use crate::sidecar;
+
+/// Prefix of query parameters that set processor options.
+const OPTION_PREFIX: &str = "opt.";
+
+/// A validated link waiting for the user, sent as `deep-link-request`.
+#[derive(Clone, Debug, Serialize)]
+pub struct DeepLinkRequest {
+    pub id: String,
+    pub processor_id: String,
+    /// English name of the processor, for display.
+    pub processor_label: String,
+    pub files: Vec<PathBuf>,
+    pub options: Map<String, Value>,
+}
+
+/// Payload of the `deep-link-rejected` event.
+#[derive(Clone, Serialize)]
+struct DeepLinkRejected {
+    url: String,
+    reason: String,
+}
+
+/// Links the user hasn't confirmed or dismissed yet.
+///
+/// Links that launch the app arrive before the webview is listening, so
+/// the frontend also asks for them through `get_pending_deep_links`.
+#[derive(Default)]
+pub struct PendingDeepLinks(Mutex<Vec<DeepLinkRequest>>);
+
+/// Check a link once the sidecar is up, then queue it and ask the user
+/// through `deep-link-request`. Invalid links are reported with
+/// `deep-link-rejected` instead.
+pub fn handle(app: &AppHandle, url: Url) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match parse(&app, &url).await {
+            Ok(request) => {
+                app.state::<PendingDeepLinks>()
+                    .0
+                    .lock()
+                    .unwrap()
+                    .push(request.clone());
+                let _ = app.emit("deep-link-request", request);
+            }
+            Err(reason) => {
+                let _ = app.emit(
+                    "deep-link-rejected",
+                    DeepLinkRejected {
+                        url: url.to_string(),
+                        reason,
+                    },
+                );
+            }
+        }
+    });
+}
+
+pub fn pending(app: &AppHandle) -> Vec<DeepLinkRequest> {
+    app.state::<PendingDeepLinks>().0.lock().unwrap().clone()
+}
+
+/// Start the job for a link the user confirmed. The link stays pending
+/// if that fails, so it can be confirmed again or dismissed.
+pub async fn confirm(app: &AppHandle, id: &str) -> Result<Submitted, String> {
+    let request = pending(app)
+        .into_iter()
+        .find(|request| request.id == id)
+        .ok_or_else(|| format!("No pending link {id}"))?;
+    let submitted = jobs::submit(
+        app,
+        request.processor_id,
+        request.files,
+        Value::Object(request.options),
+    )
+    .await?;
+    take(app, id);
+    Ok(submitted)
+}
+
+/// Drop a link the user turned down. Returns `false` if it wasn't pending.
+pub fn dismiss(app: &AppHandle, id: &str) -> bool {
+    take(app, id).is_some()
+}
+
+fn take(app: &AppHandle, id: &str) -> Option<DeepLinkRequest> {
+    let state = app.state::<PendingDeepLinks>();
+    let mut pending = state.0.lock().unwrap();
+    let index = pending.iter().position(|request| request.id == id)?;
+    Some(pending.remove(index))
+}
+
+/// What a link asks for, before it is checked.
+#[derive(Debug)]
+struct Link {
+    processor_id: String,
+    files: Vec<PathBuf>,
+    /// Option ids and their values as written in the link.
+    options: Vec<(String, String)>,
+}
+
+async fn parse(app: &AppHandle, url: &Url) -> Result<DeepLinkRequest, String> {
+    let link = read(url)?;
+    // The link may be what launched the app.
+    sidecar::wait_ready(app).await?;
+    let processor = processors::fetch(app, &link.processor_id).await?;
+    check(link, processor)
+}
+
+fn read(url: &Url) -> Result<Link, String> {
+    if url.scheme() != "vimix" || url.host_str() != Some("process") {
+        return Err("Only vimix://process links are supported".to_string());
+    }
+
+    let mut processor_id = None;
+    let mut files = Vec::new();
+    let mut options = Vec::new();
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "processor" => processor_id = Some(value.into_owned()),
+            "file" => files.push(PathBuf::from(value.as_ref())),
+            key => match key.strip_prefix(OPTION_PREFIX) {
+                Some(option) => options.push((option.to_string(), value.into_owned())),
+                None => return Err(format!("Unknown parameter {key}")),
+            },
+        }
+    }
+    let processor_id = processor_id.ok_or("Missing processor")?;
+    if files.is_empty() {
+        return Err("No files given".to_string());
+    }
+    Ok(Link {
+        processor_id,
+        files,
+        options,
+    })
+}
+
+/// Check a link against the processor's schema and the files on disk.
+fn check(link: Link, processor: Processor) -> Result<DeepLinkRequest, String> {
+    let files = link
+        .files
+        .into_iter()
+        .map(|file| check_file(file, &processor))
+        .collect::<Result<_, _>>()?;
+    let mut options = Map::new();
+    for (id, raw) in link.options {
+        let value = processor.option_value(&id, &raw)?;
+        options.insert(id, value);
+    }
+
+    Ok(DeepLinkRequest {
+        id: uuid::Uuid::new_v4().to_string(),
+        processor_id: link.processor_id,
+        processor_label: processor.label,
+        files,
+        options,
+    })
+}
+
+/// Links may only name absolute paths to files the processor accepts.
+fn check_file(path: PathBuf, processor: &Processor) -> Result<PathBuf, String> {
+    if !path.is_absolute() {
+        return Err(format!("{} is not an absolute path", path.display()));
+    }
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    if !path.is_file() {
+        return Err(format!("{}: not a file", path.display()));
+    }
+    if !processor.accepts(&path) {
+        return Err(format!(
+            "{} doesn't accept {}",
+            processor.id,
+            path.display()
+        ));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processor() -> Processor {
+        serde_json::from_value(serde_json::json!({
+            "id": "image-compress",
+            "label": "Compress images",
+            "accepted_extensions": [".png", ".jpg"],
+            "options_schema": [
+                {"id": "quality", "type": "number", "min": 1, "max": 100},
+                {"id": "width", "type": "dimension", "allow_original": true},
+                {"id": "format", "type": "select", "choices": [{"value": "webp"}, {"value": "png"}]},
+            ],
+        }))
+        .unwrap()
+    }
+
+    /// Read and check `query` against `processor()`, for a file that exists.
+    fn link(query: &str) -> Result<DeepLinkRequest, String> {
+        let dir = std::env::temp_dir().join(format!("vimix-deeplink-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("photo.png");
+        std::fs::write(&file, b"").unwrap();
+        let query = query.replace("{file}", &file.to_string_lossy());
+        let url = Url::parse(&format!("vimix://process?{query}")).unwrap();
+        check(read(&url)?, processor())
+    }
+
+    #[test]
+    fn valid_links_become_requests() {
+        let request =
+            link("processor=image-compress&file={file}&opt.quality=80&opt.width=original").unwrap();
+        assert_eq!(request.processor_id, "image-compress");
+        assert_eq!(request.processor_label, "Compress images");
+        assert!(request.files[0].ends_with("photo.png"));
+        assert_eq!(request.options["quality"], 80);
+        assert_eq!(request.options["width"], "original");
+    }
+
+    #[test]
+    fn bad_links_are_rejected() {
+        assert_eq!(
+            link("processor=image-compress&file={file}&opt.speed=2").unwrap_err(),
+            "image-compress has no option speed"
+        );
+        assert_eq!(
+            link("processor=image-compress&file=photo.png").unwrap_err(),
+            "photo.png is not an absolute path"
+        );
+        assert_eq!(
+            link("processor=image-compress&file={file}&opt.quality=high").unwrap_err(),
+            "quality must be a number, got high"
+        );
+        assert_eq!(
+            link("processor=image-compress&file={file}&verbose=1").unwrap_err(),
+            "Unknown parameter verbose"
+        );
+        assert_eq!(
+            link("processor=image-compress").unwrap_err(),
+            "No files given"
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! The sidecar without the Tauri runtime, for the command line.
+//!
+//! Launches the same bundled binary and toolchain as the app, but as a
+//! plain child process: no window, no webview and no display needed.
+
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{Method, Request};
+use serde::de::DeserializeOwned;
+
+use crate::client::{self, Endpoint};
+use crate::config::SidecarConfig;
+use crate::settings::ToolPaths;
+use crate::sidecar::{
+    self, Control, CONTROL_PREFIX, PROBE_INTERVAL, SHUTDOWN_TIMEOUT, STARTUP_TIMEOUT, TOKEN_HEADER,
+};
+use crate::toolchain;
+
+/// How to launch a headless sidecar.
+pub struct HeadlessOptions {
+    pub host: String,
+    /// `0` lets the OS pick a free port.
+    pub port: u16,
+    /// Where the sidecar keeps `uploads/` and `jobs/`.
+    pub data_dir: PathBuf,
+    /// API token every request must carry.
+    pub token: String,
+    /// Echo the sidecar's log output on stderr.
+    pub verbose: bool,
+}
+
+/// What the sidecar reported on its own output so far.
+#[derive(Default)]
+struct Reported {
+    port: Option<u16>,
+    pgid: Option<i32>,
+    /// Last line written to stderr; after a crash, the Python exception.
+    last_error: Option<String>,
+}
+
+/// A running headless sidecar. Call `shutdown` to stop it.
+pub struct HeadlessSidecar {
+    child: Child,
+    token: String,
+    endpoint: Endpoint,
+    data_dir: PathBuf,
+    reported: Arc<Mutex<Reported>>,
+}
+
+impl HeadlessSidecar {
+    /// Spawn the sidecar next to the current executable and wait until it
+    /// answers `/health`.
+    pub async fn start(options: &HeadlessOptions) -> Result<Self, String> {
+        let exe = std::env::current_exe()
+            .map_err(|e| format!("Failed to locate the Vimix binary: {e}"))?;
+        let exe_dir = exe.parent().unwrap_or(Path::new("."));
+
+        let bundled_dir = resource_dir(exe_dir).join("resources");
+        let tools = toolchain::detect(Some(&bundled_dir), &ToolPaths::default());
+        for info in tools.iter().filter(|info| !info.available) {
+            eprintln!(
+                "warning: {} not found, processors that need it will fail",
+                info.name
+            );
+        }
+
+        std::fs::create_dir_all(&options.data_dir)
+            .map_err(|e| format!("Failed to create {}: {e}", options.data_dir.display()))?;
+        let mut config = SidecarConfig::new(&tools)
+            .arg("--host")
+            .arg(&options.host)
+            .arg("--port")
+            .arg(options.port.to_string());
+        if !options.verbose {
+            config = config.arg("--log-level").arg("warning");
+        }
+        config.working_dir = Some(options.data_dir.clone());
+        config.data_dir = Some(options.data_dir.clone());
+        let config = config
+            .arg("--supervised")
+            .env("PYTHONUNBUFFERED", "1")
+            .env("VIMIX_API_TOKEN", &options.token);
+
+        let mut child = spawn(exe_dir, &config)?;
+        let reported = Arc::new(Mutex::new(Reported::default()));
+        if let Some(stdout) = child.stdout.take() {
+            read_stdout(stdout, reported.clone(), options.verbose);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            read_stderr(stderr, reported.clone(), options.verbose);
+        }
+
+        match wait_until_healthy(&mut child, &reported).await {
+            Ok(endpoint) => Ok(Self {
+                child,
+                token: options.token.clone(),
+                endpoint,
+                data_dir: options.data_dir.clone(),
+                reported,
+            }),
+            Err(reason) => {
+                let pgid = reported.lock().unwrap().pgid;
+                sidecar::kill_tree(Some(child.id()), pgid);
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(reason)
+            }
+        }
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.endpoint.port()
+    }
+
+    /// Wait until the sidecar exits on its own, reap what's left of its
+    /// process group, and describe how it ended.
+    pub async fn exited(&mut self) -> String {
+        loop {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                let reported = self.reported.lock().unwrap();
+                sidecar::kill_tree(None, reported.pgid);
+                return describe_exit(status, &reported);
+            }
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    }
+
+    /// The sidecar's job output directory; results are only read from here.
+    pub fn jobs_dir(&self) -> PathBuf {
+        self.data_dir.join("jobs")
+    }
+
+    /// Call the sidecar API and decode the JSON response, see `client::call`.
+    pub async fn call<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<T, String> {
+        client::call(&self.endpoint, &self.token, method, path, body).await
+    }
+
+    /// Stop the sidecar and everything it spawned, the same way the app
+    /// does on quit: drain through `POST /shutdown`, then kill what's left.
+    pub async fn shutdown(mut self) {
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/shutdown")
+            .header(TOKEN_HEADER, &self.token)
+            .body(Full::new(Bytes::new()))
+            .expect("valid shutdown request");
+        let _ =
+            tokio::time::timeout(Duration::from_secs(1), client::send(&self.endpoint, req)).await;
+
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while matches!(self.child.try_wait(), Ok(None)) && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let pgid = self.reported.lock().unwrap().pgid;
+        #[cfg(unix)]
+        {
+            if let Some(pgid) = pgid {
+                // SAFETY: killpg has no memory-safety preconditions.
+                unsafe { libc::killpg(pgid, libc::SIGTERM) };
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+        }
+        sidecar::kill_tree(Some(self.child.id()), pgid);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Where the app's resources live, following Tauri's bundle layout:
+/// `Contents/Resources` in a macOS app, `/usr/lib/Vimix` for Linux
+/// packages and AppImages, and next to the binary otherwise (Windows,
+/// dev builds).
+fn resource_dir(exe_dir: &Path) -> PathBuf {
+    let bundled = if cfg!(target_os = "macos") {
+        exe_dir.join("../Resources")
+    } else if cfg!(target_os = "linux") && exe_dir.ends_with("usr/bin") {
+        exe_dir.join("../lib/Vimix")
+    } else {
+        exe_dir.to_path_buf()
+    };
+    if bundled.is_dir() {
+        bundled
+    } else {
+        exe_dir.to_path_buf()
+    }
+}
+
+/// Spawn the sidecar binary that Tauri installs next to the app binary.
+fn spawn(exe_dir: &Path, config: &SidecarConfig) -> Result<Child, String> {
+    let binary = exe_dir.join(format!("Vimix-processor{}", std::env::consts::EXE_SUFFIX));
+    let mut command = Command::new(&binary);
+    command
+        .args(&config.args)
+        .envs(config.envs())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(dir) = &config.working_dir {
+        command.current_dir(dir);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(crate::sidecar::CREATE_NO_WINDOW);
+    }
+    command
+        .spawn()
+        .map_err(|e| format!("Failed to spawn {}: {e}", binary.display()))
+}
+
+/// Pick control messages out of stdout; the rest is log output.
+fn read_stdout(stdout: impl Read + Send + 'static, reported: Arc<Mutex<Reported>>, echo: bool) {
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let Some(json) = line.trim().strip_prefix(CONTROL_PREFIX) else {
+                if echo {
+                    eprintln!("{line}");
+                }
+                continue;
+            };
+            match serde_json::from_str::<Control>(json) {
+                Ok(Control::ProcessGroup { pgid }) => reported.lock().unwrap().pgid = Some(pgid),
+                Ok(Control::Listening { port, .. }) => reported.lock().unwrap().port = port,
+                Err(_) => {}
+            }
+        }
+    });
+}
+
+fn read_stderr(stderr: impl Read + Send + 'static, reported: Arc<Mutex<Reported>>, echo: bool) {
+    thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if echo {
+                eprintln!("{line}");
+            }
+            if !line.trim().is_empty() {
+                reported.lock().unwrap().last_error = Some(line.trim().to_string());
+            }
+        }
+    });
+}
+
+/// Wait for the sidecar to report its port, then poll `/health` there.
+async fn wait_until_healthy(
+    child: &mut Child,
+    reported: &Mutex<Reported>,
+) -> Result<Endpoint, String> {
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(describe_exit(status, &reported.lock().unwrap()));
+        }
+        let port = reported.lock().unwrap().port;
+        if let Some(port) = port {
+            let endpoint = Endpoint::Tcp(port);
+            let req = Request::get("/health")
+                .body(Full::new(Bytes::new()))
+                .expect("valid health request");
+            let probe = tokio::time::timeout(PROBE_INTERVAL, client::send(&endpoint, req)).await;
+            if let Ok(Ok(res)) = probe {
+                if res.status().is_success() {
+                    return Ok(endpoint);
+                }
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "Sidecar did not start within {}s",
+                STARTUP_TIMEOUT.as_secs()
+            ));
+        }
+        tokio::time::sleep(PROBE_INTERVAL).await;
+    }
+}
+
+fn describe_exit(status: ExitStatus, reported: &Reported) -> String {
+    match &reported.last_error {
+        Some(line) => format!("Sidecar exited with {status}: {line}"),
+        None => format!("Sidecar exited with {status}"),
+    }
+}
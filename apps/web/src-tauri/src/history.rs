@@ -0,0 +1,402 @@
+//! Job history, kept by the app in `history.sqlite` so it outlives the
+//! sidecar, which forgets jobs an hour after they finish.
+//!
+//! A job is recorded when `jobs::submit` creates it or when `progress`
+//! first follows it (uploads), then updated as it runs, finishes and has
+//! its result saved.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::jobs::Submitted;
+use crate::progress::JobProgress;
+
+/// Records per `list_job_history` page.
+pub const PAGE_SIZE: u32 = 50;
+
+/// Schema changes, in order; the database's `user_version` is how many
+/// have been applied.
+const MIGRATIONS: &[&str] = &[
+    "
+CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    batch_id TEXT,
+    processor_id TEXT NOT NULL,
+    inputs TEXT NOT NULL,
+    local INTEGER NOT NULL,
+    options TEXT NOT NULL,
+    status TEXT NOT NULL,
+    output_path TEXT,
+    error TEXT,
+    created_at TEXT NOT NULL,
+    started_at TEXT,
+    finished_at TEXT
+);
+CREATE INDEX IF NOT EXISTS jobs_created_at ON jobs (created_at);
+",
+    // Size and modification time of each input when submitted
+    "ALTER TABLE jobs ADD COLUMN input_stamps TEXT;",
+];
+
+const COLUMNS: &str = "id, batch_id, processor_id, inputs, local, options, status, output_path, \
+    error, created_at, started_at, finished_at, \
+    CAST((julianday(finished_at) - julianday(created_at)) * 86400000 AS INTEGER), input_stamps";
+
+/// One job as it was submitted and how it ended.
+#[derive(Clone, Debug, Serialize)]
+pub struct JobRecord {
+    pub id: String,
+    /// The batch the job was submitted in, if any.
+    pub batch_id: Option<String>,
+    pub processor_id: String,
+    /// Absolute paths of files processed in place (`local`), file names
+    /// for uploads.
+    pub inputs: Vec<String>,
+    pub local: bool,
+    pub options: Value,
+    /// `pending`, `processing`, `completed` or `failed`.
+    pub status: String,
+    /// Where the result was saved, `None` until it is. The sidecar's own
+    /// copy is deleted when the app quits, so it is never recorded.
+    pub output_path: Option<String>,
+    pub error: Option<String>,
+    /// RFC 3339 timestamps (UTC).
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// From submission to the end of the job.
+    pub duration_ms: Option<i64>,
+    /// What each of `inputs` looked like when submitted, `None` where
+    /// unknown.
+    #[serde(skip)]
+    pub input_stamps: Vec<Option<FileStamp>>,
+}
+
+/// Size and modification time of a file, to tell whether it changed
+/// without reading it again.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Nanoseconds since the Unix epoch, where the platform records it.
+    pub modified_ns: Option<u64>,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            size: metadata.len(),
+            modified_ns: metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .and_then(|since| u64::try_from(since.as_nanos()).ok()),
+        })
+    }
+}
+
+/// Which records `list_job_history` returns; every field is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HistoryFilter {
+    pub processor_id: Option<String>,
+    pub status: Option<String>,
+    /// Part of an input or output path or file name, case-insensitive.
+    pub search: Option<String>,
+    /// Records created from `since` (inclusive) until `until` (exclusive),
+    /// as RFC 3339 timestamps or `YYYY-MM-DD` dates in UTC.
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+/// One page of records, newest first.
+#[derive(Debug, Serialize)]
+pub struct HistoryPage {
+    pub records: Vec<JobRecord>,
+    /// Records matching the filter across all pages.
+    pub total: u64,
+}
+
+pub struct JobHistory(Mutex<Connection>);
+
+impl JobHistory {
+    /// Open (or create) `history.sqlite` in `dir`.
+    ///
+    /// Like the sidecar log, history is best-effort: if the database is
+    /// unusable, jobs are recorded in memory for this launch only.
+    pub fn open(dir: &Path) -> Self {
+        let conn = fs::create_dir_all(dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| Connection::open(dir.join("history.sqlite")).map_err(|e| e.to_string()))
+            .and_then(migrate)
+            .or_else(|_| {
+                Connection::open_in_memory()
+                    .map_err(|e| e.to_string())
+                    .and_then(migrate)
+            })
+            .expect("in-memory SQLite database");
+        Self(Mutex::new(conn))
+    }
+
+    /// Record the jobs `jobs::submit` created for `paths`, which looked
+    /// like `stamps` when submitted. A batch has one job per path, in order.
+    pub fn submitted(
+        &self,
+        submitted: &Submitted,
+        processor_id: &str,
+        paths: &[PathBuf],
+        stamps: &[Option<FileStamp>],
+        options: &Value,
+    ) -> Result<(), String> {
+        let paths: Vec<String> = paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        let conn = self.0.lock().unwrap();
+        let insert = |id: &str,
+                      batch_id: Option<&str>,
+                      inputs: &[String],
+                      stamps: &[Option<FileStamp>]| {
+            conn.execute(
+                "INSERT INTO jobs (id, batch_id, processor_id, inputs, local, options, status, created_at, input_stamps)
+                 VALUES (?1, ?2, ?3, ?4, 1, ?5, 'pending', ?6, ?7)
+                 ON CONFLICT (id) DO UPDATE SET
+                     batch_id = excluded.batch_id, inputs = excluded.inputs, local = 1,
+                     input_stamps = excluded.input_stamps",
+                params![
+                    id,
+                    batch_id,
+                    processor_id,
+                    serde_json::to_string(inputs).unwrap_or_default(),
+                    options.to_string(),
+                    now(),
+                    serde_json::to_string(stamps).unwrap_or_default(),
+                ],
+            )
+            .map_err(|e| e.to_string())
+        };
+        match submitted {
+            Submitted::Job { id } => {
+                insert(id, None, &paths, stamps)?;
+            }
+            Submitted::Batch { id, job_ids } => {
+                for ((job_id, path), stamp) in job_ids.iter().zip(&paths).zip(stamps) {
+                    insert(
+                        job_id,
+                        Some(id),
+                        std::slice::from_ref(path),
+                        std::slice::from_ref(stamp),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the latest state of a followed job. Jobs the app didn't
+    /// submit are added once the sidecar has described them.
+    pub fn observe(&self, job: &JobProgress, options: Option<&Value>) -> Result<(), String> {
+        let conn = self.0.lock().unwrap();
+        let now = now();
+        if let (Some(processor_id), Some(filename)) = (&job.processor_id, &job.original_filename) {
+            conn.execute(
+                "INSERT INTO jobs (id, processor_id, inputs, local, options, status, created_at)
+                 VALUES (?1, ?2, ?3, 0, ?4, ?5, ?6)
+                 ON CONFLICT (id) DO NOTHING",
+                params![
+                    job.id,
+                    processor_id,
+                    serde_json::to_string(&[filename]).unwrap_or_default(),
+                    options
+                        .cloned()
+                        .unwrap_or_else(|| Value::Object(Default::default()))
+                        .to_string(),
+                    job.status,
+                    now,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        let error = (job.status == "failed").then_some(&job.message);
+        conn.execute(
+            "UPDATE jobs SET
+                 status = ?2,
+                 started_at = CASE WHEN ?2 = 'pending' THEN started_at ELSE COALESCE(started_at, ?3) END,
+                 finished_at = CASE WHEN ?2 IN ('completed', 'failed') THEN COALESCE(finished_at, ?3) ELSE finished_at END,
+                 error = COALESCE(?4, error)
+             WHERE id = ?1",
+            params![job.id, job.status, now, error],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Record where a job's result was saved.
+    pub fn set_output(&self, job_id: &str, path: &Path) -> Result<(), String> {
+        self.0
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE jobs SET output_path = ?2 WHERE id = ?1",
+                params![job_id, path.to_string_lossy()],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Page `page` (0-based) of the records matching `filter`.
+    pub fn list(&self, filter: &HistoryFilter, page: u32) -> Result<HistoryPage, String> {
+        let mut clauses = Vec::new();
+        let mut values = Vec::new();
+        if let Some(processor_id) = &filter.processor_id {
+            clauses.push("processor_id = ?");
+            values.push(processor_id.clone());
+        }
+        if let Some(status) = &filter.status {
+            clauses.push("status = ?");
+            values.push(status.clone());
+        }
+        if let Some(search) = filter.search.as_deref().filter(|s| !s.is_empty()) {
+            clauses.push("(inputs LIKE ? ESCAPE '\\' OR output_path LIKE ? ESCAPE '\\')");
+            let pattern = format!("%{}%", escape_like(search));
+            values.push(pattern.clone());
+            values.push(pattern);
+        }
+        if let Some(since) = &filter.since {
+            clauses.push("created_at >= ?");
+            values.push(since.clone());
+        }
+        if let Some(until) = &filter.until {
+            clauses.push("created_at < ?");
+            values.push(until.clone());
+        }
+        let condition = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+
+        let conn = self.0.lock().unwrap();
+        let total: u64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM jobs {condition}"),
+                params_from_iter(&values),
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {COLUMNS} FROM jobs {condition}
+                 ORDER BY created_at DESC LIMIT {PAGE_SIZE} OFFSET {}",
+                u64::from(page) * u64::from(PAGE_SIZE)
+            ))
+            .map_err(|e| e.to_string())?;
+        let records = statement
+            .query_map(params_from_iter(&values), record)
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| e.to_string())?;
+        Ok(HistoryPage { records, total })
+    }
+
+    pub fn get(&self, job_id: &str) -> Result<Option<JobRecord>, String> {
+        self.0
+            .lock()
+            .unwrap()
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM jobs WHERE id = ?1"),
+                [job_id],
+                record,
+            )
+            .optional()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Where the latest job that read only `input` saved its result, if
+    /// `input` hasn't changed since and the result is still there.
+    pub fn saved_result(&self, input: &Path) -> Option<PathBuf> {
+        let stamp = FileStamp::of(input).ok()?;
+        let inputs = serde_json::to_string(&[input.to_string_lossy()]).ok()?;
+        let (output, stamps): (String, Option<String>) = self
+            .0
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT output_path, input_stamps FROM jobs
+                 WHERE inputs = ?1 AND output_path IS NOT NULL
+                 ORDER BY created_at DESC LIMIT 1",
+                [inputs],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .ok()??;
+        let stamps: Vec<Option<FileStamp>> = serde_json::from_str(&stamps?).ok()?;
+        let output = PathBuf::from(output);
+        (stamps == [Some(stamp)] && output.is_file()).then_some(output)
+    }
+
+    /// Forget a record. Returns `false` if there was none.
+    pub fn delete(&self, job_id: &str) -> Result<bool, String> {
+        let deleted = self
+            .0
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM jobs WHERE id = ?1", [job_id])
+            .map_err(|e| e.to_string())?;
+        Ok(deleted > 0)
+    }
+}
+
+fn migrate(conn: Connection) -> Result<Connection, String> {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(migration).map_err(|e| e.to_string())?;
+        conn.pragma_update(None, "user_version", index + 1)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(conn)
+}
+
+fn record(row: &Row) -> rusqlite::Result<JobRecord> {
+    let inputs: String = row.get(3)?;
+    let options: String = row.get(5)?;
+    let input_stamps: Option<String> = row.get(13)?;
+    Ok(JobRecord {
+        id: row.get(0)?,
+        batch_id: row.get(1)?,
+        processor_id: row.get(2)?,
+        inputs: serde_json::from_str(&inputs).unwrap_or_default(),
+        local: row.get(4)?,
+        options: serde_json::from_str(&options).unwrap_or(Value::Null),
+        status: row.get(6)?,
+        output_path: row.get(7)?,
+        error: row.get(8)?,
+        created_at: row.get(9)?,
+        started_at: row.get(10)?,
+        finished_at: row.get(11)?,
+        duration_ms: row.get(12)?,
+        input_stamps: input_stamps
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+    })
+}
+
+/// `search` as a literal `LIKE` pattern.
+fn escape_like(search: &str) -> String {
+    search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
@@ -0,0 +1,180 @@
+//! Files handed to Vimix by the OS: dropped on the window, passed on the
+//! command line or opened through a file association.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use hyper::Method;
+use serde::Serialize;
+use tauri::{AppHandle, DragDropEvent, Emitter, Manager, Window, WindowEvent};
+
+use crate::processors::Processor;
+use crate::sidecar;
+
+/// One input file with the processors that can take it.
+#[derive(Clone, Serialize)]
+pub struct InputFile {
+    pub path: PathBuf,
+    pub name: String,
+    pub size: u64,
+    pub mime: Option<&'static str>,
+    /// Ids of the processors that accept this file's extension.
+    pub processors: Vec<String>,
+}
+
+/// Payload of the `files-dropped` event.
+#[derive(Clone, Serialize)]
+struct FilesDropped {
+    files: Vec<InputFile>,
+}
+
+/// Files opened with Vimix that the frontend hasn't picked up yet.
+///
+/// They arrive before the webview and the sidecar are up, so they are
+/// queued here and classified once the frontend asks for them.
+#[derive(Default)]
+pub struct PendingInputs(Mutex<Vec<PathBuf>>);
+
+/// Window event hook: turn OS file drops into a `files-dropped` event.
+pub fn handle_drop(window: &Window, event: &WindowEvent) {
+    let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event else {
+        return;
+    };
+    let app = window.app_handle().clone();
+    let paths = paths.clone();
+    tauri::async_runtime::spawn(async move {
+        let files = classify(&app, paths).await;
+        let _ = app.emit("files-dropped", FilesDropped { files });
+    });
+}
+
+/// Existing files among command-line arguments; flags and URLs are skipped.
+///
+/// Relative paths are resolved against `cwd`, the working directory of
+/// the process that received them.
+pub fn paths_from_args(args: impl IntoIterator<Item = OsString>, cwd: &Path) -> Vec<PathBuf> {
+    args.into_iter()
+        .filter(|arg| {
+            let arg = arg.to_string_lossy();
+            !arg.starts_with('-') && !arg.contains("://")
+        })
+        .map(|arg| cwd.join(arg))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// Queue files opened with Vimix and tell the frontend with `files-opened`;
+/// it collects them through `take_pending_inputs`.
+pub fn open(app: &AppHandle, paths: Vec<PathBuf>) {
+    if paths.is_empty() {
+        return;
+    }
+    app.state::<PendingInputs>().0.lock().unwrap().extend(paths);
+    let _ = app.emit("files-opened", ());
+}
+
+/// Drain the queued files and classify them against the processors.
+pub async fn take_pending(app: &AppHandle) -> Vec<InputFile> {
+    let paths = std::mem::take(&mut *app.state::<PendingInputs>().0.lock().unwrap());
+    if paths.is_empty() {
+        return Vec::new();
+    }
+    classify(app, paths).await
+}
+
+/// Resolve `paths` to files and suggest processors for each.
+async fn classify(app: &AppHandle, paths: Vec<PathBuf>) -> Vec<InputFile> {
+    // Without the sidecar the files are still reported, just unclassified.
+    let processors: Vec<Processor> = sidecar::call(app, Method::GET, "/processors", None)
+        .await
+        .unwrap_or_default();
+    resolve(paths)
+        .into_iter()
+        .filter_map(|path| describe(path, &processors))
+        .collect()
+}
+
+/// Canonicalize the given paths. Folders contribute the files directly
+/// inside them, in name order; hidden files are skipped.
+pub fn resolve(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        let Ok(path) = path.canonicalize() else {
+            continue;
+        };
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(&path)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|entry| entry.is_file() && !is_hidden(entry))
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    files
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+fn describe(path: PathBuf, processors: &[Processor]) -> Option<InputFile> {
+    let size = fs::metadata(&path).ok()?.len();
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    let ext = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy().to_lowercase()));
+    let processors = match &ext {
+        Some(ext) => processors
+            .iter()
+            .filter(|p| p.accepted_extensions.contains(ext))
+            .map(|p| p.id.clone())
+            .collect(),
+        None => Vec::new(),
+    };
+    Some(InputFile {
+        mime: ext.as_deref().and_then(mime_type),
+        path,
+        name,
+        size,
+        processors,
+    })
+}
+
+/// MIME type of the media formats the processors work with.
+fn mime_type(ext: &str) -> Option<&'static str> {
+    Some(match ext {
+        ".jpg" | ".jpeg" => "image/jpeg",
+        ".png" => "image/png",
+        ".webp" => "image/webp",
+        ".gif" => "image/gif",
+        ".bmp" => "image/bmp",
+        ".tif" | ".tiff" => "image/tiff",
+        ".avif" => "image/avif",
+        ".heic" => "image/heic",
+        ".svg" => "image/svg+xml",
+        ".mp4" | ".m4v" => "video/mp4",
+        ".mov" => "video/quicktime",
+        ".webm" => "video/webm",
+        ".avi" => "video/x-msvideo",
+        ".mkv" => "video/x-matroska",
+        ".mp3" => "audio/mpeg",
+        ".aac" => "audio/aac",
+        ".wav" => "audio/wav",
+        ".flac" => "audio/flac",
+        ".ogg" => "audio/ogg",
+        ".m4a" => "audio/mp4",
+        ".wma" => "audio/x-ms-wma",
+        ".pdf" => "application/pdf",
+        ".zip" => "application/zip",
+        _ => return None,
+    })
+}
@@ -0,0 +1,224 @@
+//! Jobs as seen from the app: submission by file path, for media that
+//! already sits on local disk, and lookup of finished results.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use hyper::Method;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::history::{FileStamp, JobHistory};
+use crate::{progress, sidecar};
+
+/// What `submit_job` created, mirroring the sidecar's `/jobs/batch` reply.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Submitted {
+    /// One job, for a single file or a multi-file processor.
+    Job { id: String },
+    /// One job per file.
+    Batch { id: String, job_ids: Vec<String> },
+}
+
+/// Check that every path is a readable regular file and make it absolute.
+fn validate(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>, String> {
+    if paths.is_empty() {
+        return Err("No files given".to_string());
+    }
+    paths
+        .into_iter()
+        .map(|path| {
+            let resolved = path
+                .canonicalize()
+                .map_err(|e| format!("{}: {e}", path.display()))?;
+            if !resolved.is_file() {
+                return Err(format!("{}: not a file", path.display()));
+            }
+            File::open(&resolved).map_err(|e| format!("{}: {e}", path.display()))?;
+            Ok(resolved)
+        })
+        .collect()
+}
+
+/// Validate `paths` and hand them to the sidecar by reference.
+///
+/// The sidecar reads the files in place, so nothing is sent through the
+/// webview or copied into its `uploads/` dir. The new jobs are recorded
+/// in the history and followed right away, see `progress`.
+pub async fn submit(
+    app: &AppHandle,
+    processor_id: String,
+    paths: Vec<PathBuf>,
+    options: serde_json::Value,
+) -> Result<Submitted, String> {
+    let paths = validate(paths)?;
+    // Taken before the sidecar reads the files, for `rerun`
+    let stamps: Vec<Option<FileStamp>> =
+        paths.iter().map(|path| FileStamp::of(path).ok()).collect();
+    let body = serde_json::json!({
+        "processor_id": processor_id,
+        "paths": paths,
+        "options": options,
+    });
+    let submitted = sidecar::call(app, Method::POST, "/jobs/local", Some(&body)).await?;
+    let history = app.state::<JobHistory>();
+    let _ = history.submitted(&submitted, &processor_id, &paths, &stamps, &options);
+    progress::track_submitted(app, &submitted);
+    Ok(submitted)
+}
+
+/// Submit a past job again, on the same files with its options and
+/// `overrides` on top (`null` removes an option).
+///
+/// Only jobs that read their files in place can be rerun, and only while
+/// the files are still there and unchanged.
+pub async fn rerun(
+    app: &AppHandle,
+    job_id: &str,
+    overrides: serde_json::Map<String, serde_json::Value>,
+) -> Result<Submitted, String> {
+    let record = app
+        .state::<JobHistory>()
+        .get(job_id)?
+        .ok_or_else(|| format!("No record of job {job_id}"))?;
+    if !record.local {
+        return Err(format!(
+            "Job {job_id} was uploaded, its files aren't kept to run it again"
+        ));
+    }
+
+    let paths: Vec<PathBuf> = record.inputs.iter().map(PathBuf::from).collect();
+    unchanged(&paths, &record.input_stamps)?;
+
+    let mut options = match record.options {
+        serde_json::Value::Object(options) => options,
+        _ => serde_json::Map::new(),
+    };
+    for (key, value) in overrides {
+        if value.is_null() {
+            options.remove(&key);
+        } else {
+            options.insert(key, value);
+        }
+    }
+    submit(
+        app,
+        record.processor_id,
+        paths,
+        serde_json::Value::Object(options),
+    )
+    .await
+}
+
+/// Check that `paths` still exist with the size and modification time
+/// they had when submitted. Paths without a stamp are refused rather than
+/// assumed unchanged.
+fn unchanged(paths: &[PathBuf], stamps: &[Option<FileStamp>]) -> Result<(), String> {
+    for (index, path) in paths.iter().enumerate() {
+        if !path.is_file() {
+            return Err(format!("{} no longer exists", path.display()));
+        }
+        let Some(Some(expected)) = stamps.get(index) else {
+            return Err(format!(
+                "Can't tell whether {} changed since the job ran",
+                path.display()
+            ));
+        };
+        let actual = FileStamp::of(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        if actual != *expected {
+            return Err(format!("{} has changed since the job ran", path.display()));
+        }
+    }
+    Ok(())
+}
+
+/// The parts of a sidecar job the app needs.
+#[derive(Clone, Debug, Deserialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub processor_id: String,
+    pub original_filename: String,
+    pub status: String,
+    #[serde(default)]
+    pub progress: f64,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub result_path: Option<PathBuf>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// What the job was submitted with.
+    #[serde(default)]
+    pub options: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct BatchInfo {
+    jobs: Vec<JobInfo>,
+}
+
+pub async fn fetch(app: &AppHandle, job_id: &str) -> Result<JobInfo, String> {
+    sidecar::call(app, Method::GET, &format!("/jobs/{job_id}"), None).await
+}
+
+pub async fn fetch_batch(app: &AppHandle, batch_id: &str) -> Result<Vec<JobInfo>, String> {
+    let batch: BatchInfo =
+        sidecar::call(app, Method::GET, &format!("/jobs/batch/{batch_id}"), None).await?;
+    Ok(batch.jobs)
+}
+
+/// The result file of a completed job.
+///
+/// The path comes from the sidecar, so it is only trusted once it has been
+/// resolved to a file inside the sidecar's jobs directory.
+pub fn result_file(app: &AppHandle, job: &JobInfo) -> Result<PathBuf, String> {
+    result_in(&sidecar::jobs_dir(app)?, job)
+}
+
+/// Same as `result_file`, for a sidecar keeping its jobs in `jobs_dir`.
+pub fn result_in(jobs_dir: &Path, job: &JobInfo) -> Result<PathBuf, String> {
+    if job.status != "completed" {
+        return Err(format!("Job {} has no result yet", job.id));
+    }
+    let path = job
+        .result_path
+        .as_deref()
+        .ok_or_else(|| format!("Job {} has no result", job.id))?;
+    inside(jobs_dir, path).ok_or_else(|| format!("Result of job {} is not available", job.id))
+}
+
+/// Look up a job and return its result file, see `result_file`.
+pub async fn result_path(app: &AppHandle, job_id: &str) -> Result<PathBuf, String> {
+    let job = fetch(app, job_id).await?;
+    result_file(app, &job)
+}
+
+fn inside(dir: &Path, path: &Path) -> Option<PathBuf> {
+    let dir = dir.canonicalize().ok()?;
+    let path = path.canonicalize().ok()?;
+    (path.starts_with(&dir) && path.is_file()).then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reruns_need_unchanged_files() {
+        let dir = std::env::temp_dir().join(format!("vimix-unchanged-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("photo.png");
+        std::fs::write(&path, b"before").unwrap();
+        let paths = [path.clone()];
+        let stamp = FileStamp::of(&path).unwrap();
+
+        assert!(unchanged(&paths, &[Some(stamp.clone())]).is_ok());
+        assert!(unchanged(&paths, &[None]).is_err());
+        assert!(unchanged(&paths, &[]).is_err());
+        std::fs::write(&path, b"edited").unwrap();
+        assert!(unchanged(&paths, &[Some(stamp.clone())]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(unchanged(&paths, &[Some(stamp)]).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! JSON files the app keeps in its config dir: `settings.json`,
+//! `presets.json` and the like.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+/// Read `path` and turn its text into a `T` with `parse`.
+///
+/// A missing file gives `None`. An unreadable one is moved aside to
+/// `<file>.bak` so the next save doesn't silently replace it, and gives
+/// `None` too.
+pub fn load<T>(path: &Path, parse: impl FnOnce(&str) -> Result<T, String>) -> Option<T> {
+    let text = fs::read_to_string(path).ok()?;
+    match parse(&text) {
+        Ok(value) => Some(value),
+        Err(_) => {
+            let _ = fs::rename(path, with_suffix(path, ".bak"));
+            None
+        }
+    }
+}
+
+/// Like `load`, but leaves an unreadable file where it is and returns
+/// why it couldn't be read, for callers that only look.
+pub fn read<T>(
+    path: &Path,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<Option<T>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse(&text).map(Some),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {e}", path.display())),
+    }
+}
+
+/// Write `value` to `path` as pretty-printed JSON, creating its folder.
+///
+/// Goes through a temporary file so a crash can't leave half a file.
+pub fn write(path: &Path, value: &impl Serialize) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    let tmp = with_suffix(path, ".tmp");
+    fs::write(&tmp, json).map_err(|e| format!("Failed to write {}: {e}", tmp.display()))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// `path` with `suffix` appended to its file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_files_are_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("vimix-json-file-{}", std::process::id()));
+        let path = dir.join("settings.json");
+        write(&path, &serde_json::json!({ "version": 1 })).unwrap();
+        let parse =
+            |text: &str| serde_json::from_str::<serde_json::Value>(text).map_err(|e| e.to_string());
+        assert_eq!(
+            load(&path, parse),
+            Some(serde_json::json!({ "version": 1 }))
+        );
+
+        fs::write(&path, "{ half").unwrap();
+        assert_eq!(load(&path, parse), None);
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("settings.json.bak")).unwrap(),
+            "{ half"
+        );
+        assert_eq!(load(&path, parse), None);
+
+        fs::write(&path, "{ half").unwrap();
+        assert!(read(&path, parse).is_err());
+        assert!(path.exists());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read(&path, parse), Ok(None));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
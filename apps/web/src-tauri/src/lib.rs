@@ -1,71 +1,650 @@
-use tauri::Manager;
-use tauri_plugin_shell::ShellExt;
+mod cli;
+mod client;
+mod config;
+mod deeplink;
+mod headless;
+mod history;
+mod inputs;
+mod jobs;
+mod json_file;
+mod logs;
+mod pipelines;
+mod presets;
+mod processors;
+mod progress;
+mod proxy;
+mod results;
+mod serve;
+mod settings;
+mod sidecar;
+mod toolchain;
+mod watch;
 
-/// Holds the API port so the frontend can query it via IPC.
-struct ApiPort(u16);
+use std::path::PathBuf;
+
+use client::Transport;
+use deeplink::{DeepLinkRequest, PendingDeepLinks};
+use history::{HistoryFilter, HistoryPage, JobHistory, JobRecord};
+use inputs::{InputFile, PendingInputs};
+use logs::{LogLevel, LogLine, SidecarLogs};
+use pipelines::{Engine, Pipeline, PipelineRun, PipelineStore};
+use presets::{AppliedPreset, Preset, PresetStore};
+use progress::{ActiveJobs, JobProgress};
+use results::{OnConflict, SaveMode, SaveOptions, SavedResult};
+use serde::Serialize;
+use settings::{Settings, SettingsStore};
+use sidecar::{ApiPort, ApiToken, Backend, BackendStatus, Sidecar};
+use tauri::{Emitter, Manager, RunEvent};
+#[cfg(desktop)]
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_opener::OpenerExt;
+use toolchain::{ToolInfo, Toolchain};
+use watch::{WatchFolder, WatchStatus, WatchStore};
 
 /// Tauri command: returns the sidecar API port to the frontend.
+///
+/// Fails until the sidecar has reported the port it is listening on.
+#[tauri::command]
+fn get_api_port(state: tauri::State<ApiPort>) -> Result<u16, String> {
+    state
+        .0
+        .lock()
+        .unwrap()
+        .ok_or_else(|| "Backend is not ready yet".to_string())
+}
+
+/// Port and token the frontend needs to talk to the sidecar.
+#[derive(Serialize)]
+struct ApiCredentials {
+    /// `None` until the sidecar has reported the port it is listening on,
+    /// and always with the Unix transport.
+    port: Option<u16>,
+    token: String,
+    /// With `unix`, requests go through the `vimix-api://localhost` proxy.
+    transport: Transport,
+}
+
+/// Tauri command: returns the sidecar port and per-launch API token.
+///
+/// Every request except `/health` must carry the token in the
+/// `X-Vimix-Token` header (or a `token` query parameter where headers
+/// can't be set, e.g. `EventSource` and media URLs).
+#[tauri::command]
+fn get_api_credentials(
+    port: tauri::State<ApiPort>,
+    token: tauri::State<ApiToken>,
+    transport: tauri::State<Transport>,
+) -> ApiCredentials {
+    ApiCredentials {
+        port: *port.0.lock().unwrap(),
+        token: token.0.clone(),
+        transport: *transport,
+    }
+}
+
+/// Tauri command: returns whether the sidecar is starting, ready or failed.
+///
+/// The frontend calls this once on load, then follows the
+/// `backend-ready` / `backend-failed` events.
+#[tauri::command]
+fn get_backend_status(state: tauri::State<Backend>) -> BackendStatus {
+    state.0.lock().unwrap().clone()
+}
+
+/// Tauri command: returns the most recent sidecar log lines.
+///
+/// `tail` defaults to 200 lines and `level` to `info`. New lines are
+/// streamed live through the `sidecar-log` event.
+#[tauri::command]
+fn get_sidecar_logs(
+    state: tauri::State<SidecarLogs>,
+    tail: Option<usize>,
+    level: Option<LogLevel>,
+) -> Vec<LogLine> {
+    state.tail(tail.unwrap_or(200), level.unwrap_or(LogLevel::Info))
+}
+
+/// Tauri command: returns where ffmpeg, ffprobe and img2webp were found,
+/// their versions, and which ones are unavailable.
+///
+/// Filled in before each sidecar launch; `toolchain-unavailable` reports
+/// missing tools as they are detected.
+#[tauri::command]
+fn get_toolchain_info(state: tauri::State<Toolchain>) -> Vec<ToolInfo> {
+    state.0.lock().unwrap().clone()
+}
+
+/// Tauri command: starts processing files that are already on disk.
+///
+/// `paths` are checked here and read in place by the sidecar instead of
+/// being uploaded. With several paths and a single-file processor, one job
+/// per file is created under a batch.
+#[tauri::command]
+async fn submit_job(
+    app: tauri::AppHandle,
+    processor_id: String,
+    paths: Vec<PathBuf>,
+    options: Option<serde_json::Value>,
+) -> Result<jobs::Submitted, String> {
+    let options = options.unwrap_or_else(|| serde_json::json!({}));
+    jobs::submit(&app, processor_id, paths, options).await
+}
+
+/// Tauri command: runs a past job again on the same files, with its
+/// options and `option_overrides` on top (`null` removes an option).
+///
+/// Works for jobs started from files on disk (`submit_job`, `vimix://`
+/// links) as long as the files exist and haven't changed since.
+#[tauri::command]
+async fn rerun_job(
+    app: tauri::AppHandle,
+    job_id: String,
+    option_overrides: Option<serde_json::Map<String, serde_json::Value>>,
+) -> Result<jobs::Submitted, String> {
+    jobs::rerun(&app, &job_id, option_overrides.unwrap_or_default()).await
+}
+
+/// Tauri command: follows a job's progress, e.g. one created by an upload.
+///
+/// Progress is broadcast through `job-progress` and the final state through
+/// `job-finished`, once per job however many views are showing it. Jobs
+/// started with `submit_job` are followed without calling this.
+#[tauri::command]
+fn track_job(app: tauri::AppHandle, job_id: String) {
+    progress::track(&app, job_id);
+}
+
+/// Tauri command: returns the latest state of every job that is followed
+/// and hasn't finished yet, oldest first.
 #[tauri::command]
-fn get_api_port(state: tauri::State<ApiPort>) -> u16 {
-    state.0
+fn list_active_jobs(app: tauri::AppHandle) -> Vec<JobProgress> {
+    progress::active(&app)
 }
 
-/// Find a free TCP port for the sidecar API.
-fn find_free_port() -> u16 {
-    portpicker::pick_unused_port().expect("No free port available")
+/// Tauri command: returns one page of the job history, newest first.
+///
+/// `page` is 0-based and defaults to the first; every `filter` field is
+/// optional. Jobs are kept across launches, unlike in the sidecar.
+#[tauri::command]
+fn list_job_history(
+    state: tauri::State<JobHistory>,
+    filter: Option<HistoryFilter>,
+    page: Option<u32>,
+) -> Result<HistoryPage, String> {
+    state.list(&filter.unwrap_or_default(), page.unwrap_or(0))
 }
 
-/// Start the Python sidecar on the given port.
+/// Tauri command: returns the history record of one job, or `null` if
+/// there is none.
+#[tauri::command]
+fn get_job_record(
+    state: tauri::State<JobHistory>,
+    job_id: String,
+) -> Result<Option<JobRecord>, String> {
+    state.get(&job_id)
+}
+
+/// Tauri command: removes a job from the history. Saved results are left
+/// alone. Returns `false` if there was no record.
+#[tauri::command]
+fn delete_job_record(state: tauri::State<JobHistory>, job_id: String) -> Result<bool, String> {
+    state.delete(&job_id)
+}
+
+/// Tauri command: saves a finished job's result into `dest_dir`.
 ///
-/// Resolves bundled ffmpeg/ffprobe/img2webp paths from Tauri resources
-/// and passes them to the sidecar via environment variables so the
-/// Python `binary_paths` module can find them.
-fn spawn_sidecar(app: &tauri::AppHandle, port: u16) {
-    // Resolve resource directory to find bundled binaries
-    let resource_dir = app
-        .path()
-        .resource_dir()
-        .expect("Failed to resolve resource directory");
+/// `naming_template` defaults to `{stem}_{processor}.{ext}`; `on_conflict`
+/// (`overwrite`, `rename` or `skip`) to `rename`; `mode` (`copy` or
+/// `move`) to `copy`.
+#[tauri::command]
+async fn save_job_result(
+    app: tauri::AppHandle,
+    job_id: String,
+    dest_dir: PathBuf,
+    naming_template: Option<String>,
+    on_conflict: Option<OnConflict>,
+    mode: Option<SaveMode>,
+) -> Result<SavedResult, String> {
+    let options = SaveOptions {
+        template: naming_template,
+        on_conflict: on_conflict.unwrap_or_default(),
+        mode: mode.unwrap_or_default(),
+    };
+    results::save_job(&app, &job_id, &dest_dir, &options).await
+}
 
-    let ext = if cfg!(windows) { ".exe" } else { "" };
-    let ffmpeg_path = resource_dir.join("resources").join(format!("ffmpeg{ext}"));
-    let ffprobe_path = resource_dir.join("resources").join(format!("ffprobe{ext}"));
-    let img2webp_path = resource_dir.join("resources").join(format!("img2webp{ext}"));
+/// Tauri command: saves every finished result of a batch into `dest_dir`.
+///
+/// Same options as `save_job_result`, plus `{index}` in the template.
+/// Jobs without a result are returned as skipped.
+#[tauri::command]
+async fn save_batch_results(
+    app: tauri::AppHandle,
+    batch_id: String,
+    dest_dir: PathBuf,
+    naming_template: Option<String>,
+    on_conflict: Option<OnConflict>,
+    mode: Option<SaveMode>,
+) -> Result<Vec<SavedResult>, String> {
+    let options = SaveOptions {
+        template: naming_template,
+        on_conflict: on_conflict.unwrap_or_default(),
+        mode: mode.unwrap_or_default(),
+    };
+    results::save_batch(&app, &batch_id, &dest_dir, &options).await
+}
 
-    // Set env vars so the Python sidecar can find the bundled binaries
-    std::env::set_var("FFMPEG_BIN", &ffmpeg_path);
-    std::env::set_var("FFPROBE_BIN", &ffprobe_path);
-    std::env::set_var("IMG2WEBP_BIN", &img2webp_path);
+/// Tauri command: shows a finished job's result in the system file manager.
+#[tauri::command]
+async fn reveal_result(app: tauri::AppHandle, job_id: String) -> Result<(), String> {
+    let path = jobs::result_path(&app, &job_id).await?;
+    app.opener()
+        .reveal_item_in_dir(path)
+        .map_err(|e| e.to_string())
+}
 
-    let shell = app.shell();
-    let sidecar = shell
-        .sidecar("Vimix-processor")
-        .expect("Failed to locate sidecar binary")
-        .args(["--port", &port.to_string()]);
+/// Tauri command: opens a finished job's result with the default app.
+#[tauri::command]
+async fn open_result(app: tauri::AppHandle, job_id: String) -> Result<(), String> {
+    let path = jobs::result_path(&app, &job_id).await?;
+    app.opener()
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|e| e.to_string())
+}
+
+/// Tauri command: returns the files Vimix was opened with (command line,
+/// "Open with", file associations) that haven't been handled yet.
+///
+/// Call it once the backend is ready, and again on `files-opened`. Each
+/// file comes with the processors that accept it.
+#[tauri::command]
+async fn take_pending_inputs(app: tauri::AppHandle) -> Vec<InputFile> {
+    inputs::take_pending(&app).await
+}
+
+/// Tauri command: returns the `vimix://` links waiting for the user to
+/// confirm them.
+///
+/// Call it once on load, then follow `deep-link-request`.
+#[tauri::command]
+fn get_pending_deep_links(app: tauri::AppHandle) -> Vec<DeepLinkRequest> {
+    deeplink::pending(&app)
+}
 
-    let (mut _rx, child) = sidecar.spawn().expect("Failed to spawn sidecar");
+/// Tauri command: starts the job a `vimix://` link asked for, once the
+/// user has agreed to it.
+#[tauri::command]
+async fn confirm_deep_link(app: tauri::AppHandle, id: String) -> Result<jobs::Submitted, String> {
+    deeplink::confirm(&app, &id).await
+}
 
-    // Keep the child handle alive for the app's lifetime.
-    // Tauri automatically kills child processes on app exit.
-    Box::leak(Box::new(child));
+/// Tauri command: discards a `vimix://` link the user turned down.
+/// Returns `false` if it was no longer pending.
+#[tauri::command]
+fn dismiss_deep_link(app: tauri::AppHandle, id: String) -> bool {
+    deeplink::dismiss(&app, &id)
+}
+
+/// Tauri command: saves a named set of options for a processor.
+///
+/// The options are checked against the processor's schema. Pass `id` to
+/// update a preset; saving under an existing name for the same processor
+/// replaces that preset.
+#[tauri::command]
+async fn save_preset(
+    app: tauri::AppHandle,
+    id: Option<String>,
+    name: String,
+    processor_id: String,
+    options: serde_json::Map<String, serde_json::Value>,
+) -> Result<Preset, String> {
+    presets::save(&app, id, name, processor_id, options).await
+}
+
+/// Tauri command: returns the presets of a processor, or all of them
+/// without `processor_id`, sorted by name.
+#[tauri::command]
+fn list_presets(state: tauri::State<PresetStore>, processor_id: Option<String>) -> Vec<Preset> {
+    state.list(processor_id.as_deref())
+}
+
+/// Tauri command: deletes a preset. Returns `false` if there was none.
+#[tauri::command]
+fn delete_preset(state: tauri::State<PresetStore>, id: String) -> Result<bool, String> {
+    state.delete(&id)
+}
+
+/// Tauri command: returns a preset's options, checked against the
+/// processor's current schema, ready for the options form or `submit_job`.
+///
+/// Options the processor no longer takes are left out and listed in
+/// `skipped`.
+#[tauri::command]
+async fn apply_preset(app: tauri::AppHandle, id: String) -> Result<AppliedPreset, String> {
+    presets::apply(&app, &id).await
+}
+
+/// Tauri command: writes presets to a JSON file to share them. Exports
+/// all presets when `ids` is empty or missing. Returns how many were written.
+#[tauri::command]
+fn export_presets(
+    state: tauri::State<PresetStore>,
+    path: PathBuf,
+    ids: Option<Vec<String>>,
+) -> Result<usize, String> {
+    state.export(&ids.unwrap_or_default(), &path)
+}
+
+/// Tauri command: adds the presets from a file written by `export_presets`.
+///
+/// Every preset must be valid for its processor, or nothing is imported.
+/// An imported preset replaces one with the same name and processor.
+#[tauri::command]
+async fn import_presets(app: tauri::AppHandle, path: PathBuf) -> Result<Vec<Preset>, String> {
+    presets::import(&app, &path).await
+}
+
+/// Tauri command: returns the watch folders and whether each is running.
+#[tauri::command]
+fn list_watches(state: tauri::State<WatchStore>) -> Vec<WatchStatus> {
+    state.list()
+}
+
+/// Tauri command: adds a watch folder (without `id`) or updates one, and
+/// starts it if enabled.
+///
+/// New files matching `pattern` are processed once they stop changing and
+/// their results saved to `output_dir`. Follow `watch-processed` and
+/// `watch-failed` for each file.
+#[tauri::command]
+async fn save_watch(app: tauri::AppHandle, watch: WatchFolder) -> Result<WatchStatus, String> {
+    watch::save(&app, watch).await
+}
+
+/// Tauri command: stops and removes a watch folder. Returns `false` if
+/// there was none.
+#[tauri::command]
+fn remove_watch(app: tauri::AppHandle, id: String) -> Result<bool, String> {
+    watch::remove(&app, &id)
+}
+
+/// Tauri command: adds a pipeline (without `id`) or updates one.
+///
+/// Every step's options are checked against its processor. Saving under
+/// an existing name replaces that pipeline.
+#[tauri::command]
+async fn save_pipeline(app: tauri::AppHandle, pipeline: Pipeline) -> Result<Pipeline, String> {
+    pipelines::save(&app, pipeline).await
+}
+
+/// Tauri command: returns the pipelines, sorted by name.
+#[tauri::command]
+fn list_pipelines(state: tauri::State<PipelineStore>) -> Vec<Pipeline> {
+    state.list()
+}
+
+/// Tauri command: deletes a pipeline. Returns `false` if there was none.
+#[tauri::command]
+fn delete_pipeline(state: tauri::State<PipelineStore>, id: String) -> Result<bool, String> {
+    state.delete(&id)
+}
+
+/// Tauri command: runs each file through a pipeline, one after the other,
+/// and saves the final results into `dest_dir`.
+///
+/// Same naming options as `save_job_result`; `{processor}` is the last
+/// step's and `{index}` the position of the file in `paths`. Follow
+/// `pipeline-progress` for each run. A failed step stops that file only;
+/// the returned runs say which step failed and why.
+#[tauri::command]
+async fn run_pipeline(
+    app: tauri::AppHandle,
+    id: String,
+    paths: Vec<PathBuf>,
+    dest_dir: PathBuf,
+    naming_template: Option<String>,
+    on_conflict: Option<OnConflict>,
+) -> Result<Vec<PipelineRun>, String> {
+    let pipeline = app
+        .state::<PipelineStore>()
+        .find(&id)
+        .ok_or_else(|| format!("No pipeline {id}"))?;
+    let pipeline = pipelines::check(Engine::App(&app), pipeline).await?;
+    if paths.is_empty() {
+        return Err("No files given".to_string());
+    }
+    let options = SaveOptions {
+        template: naming_template,
+        on_conflict: on_conflict.unwrap_or_default(),
+        mode: SaveMode::default(),
+    };
+    let mut runs = Vec::with_capacity(paths.len());
+    for (index, path) in paths.iter().enumerate() {
+        let run = pipelines::run(
+            Engine::App(&app),
+            &pipeline,
+            path,
+            &dest_dir,
+            &options,
+            Some(index + 1),
+            |run| {
+                let _ = app.emit("pipeline-progress", run);
+            },
+        )
+        .await;
+        runs.push(run);
+    }
+    Ok(runs)
+}
+
+/// Tauri command: returns the user settings.
+#[tauri::command]
+fn get_settings(state: tauri::State<SettingsStore>) -> Settings {
+    state.get()
+}
+
+/// Tauri command: validates and saves the user settings.
+///
+/// When anything changed the sidecar is restarted in the background to
+/// apply them; follow `backend-ready` / `backend-failed` for the outcome.
+/// A new `sidecar.transport` only applies on the next launch of the app.
+///
+/// Restarting cancels running jobs and deletes the results that haven't
+/// been saved yet, so while jobs are running the settings are refused
+/// unless `force` is set.
+#[tauri::command]
+fn update_settings(
+    app: tauri::AppHandle,
+    settings: Settings,
+    force: Option<bool>,
+) -> Result<Settings, String> {
+    let store = app.state::<SettingsStore>();
+    settings.validate()?;
+    let running = progress::active(&app).len();
+    if running > 0 && !force.unwrap_or(false) && settings != store.get() {
+        return Err(format!(
+            "Applying the settings restarts the backend and cancels {running} running job(s)"
+        ));
+    }
+    if store.update(settings)? {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move { sidecar::reload(&app).await });
+    }
+    Ok(store.get())
+}
+
+/// Tauri command: starts the sidecar again after it failed to start
+/// (e.g. no port could be bound). Returns `false` if it isn't in the failed state.
+#[tauri::command]
+fn restart_backend(app: tauri::AppHandle) -> bool {
+    sidecar::restart(&app)
+}
+
+/// Run a command-line subcommand such as `vimix process …` without
+/// opening a window.
+///
+/// Returns the exit code, or `None` when the app should start normally.
+pub fn run_cli() -> Option<i32> {
+    cli::run(std::env::args_os().collect())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let port = find_free_port();
+    let builder = tauri::Builder::default();
+
+    // Must be the first plugin: a second launch hands its arguments to the
+    // running instance and exits before spawning another sidecar.
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.unminimize();
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        let args = argv.into_iter().skip(1).map(std::ffi::OsString::from);
+        inputs::open(
+            app,
+            inputs::paths_from_args(args, std::path::Path::new(&cwd)),
+        );
+    }));
 
-    tauri::Builder::default()
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_deep_link::init());
+
+    builder
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
-        .manage(ApiPort(port))
-        .setup(move |app| {
-            // Start the Python backend sidecar
-            spawn_sidecar(app.handle(), port);
+        .manage(ApiPort::default())
+        .manage(ApiToken::generate())
+        .manage(Sidecar::default())
+        .manage(Backend::default())
+        .manage(Toolchain::default())
+        .manage(PendingInputs::default())
+        .manage(PendingDeepLinks::default())
+        .manage(ActiveJobs::default())
+        .register_asynchronous_uri_scheme_protocol("vimix-api", |ctx, request, responder| {
+            // Proxy `vimix-api://localhost/<path>` to the sidecar (Unix socket transport)
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                responder.respond(proxy::forward(&app, request).await);
+            });
+        })
+        .on_window_event(inputs::handle_drop)
+        .setup(|app| {
+            // Load user settings; the transport is fixed for this launch
+            let config_dir = app.path().app_config_dir()?;
+            let settings = SettingsStore::load(&config_dir);
+            app.manage(sidecar::transport(&settings.get()));
+            app.manage(settings);
+            app.manage(PresetStore::load(&config_dir));
+            app.manage(WatchStore::load(&config_dir));
+            app.manage(PipelineStore::load(&config_dir));
+
+            // Capture sidecar output under the app log dir
+            let log_dir = app.path().app_log_dir()?;
+            app.manage(SidecarLogs::open(&log_dir));
+
+            // Job history outlives the sidecar's in-memory jobs
+            app.manage(JobHistory::open(&app.path().app_data_dir()?));
+
+            // Start the Python backend sidecar under supervision
+            sidecar::start(app.handle());
+
+            // Watch folders wait for the sidecar before submitting anything
+            watch::start_all(app.handle());
+
+            // Files passed on the command line ("Open with" on Windows/Linux)
+            let cwd = std::env::current_dir().unwrap_or_default();
+            inputs::open(
+                app.handle(),
+                inputs::paths_from_args(std::env::args_os().skip(1), &cwd),
+            );
+
+            // `vimix://` links, including the one that launched the app.
+            // Later launches forward theirs through the single-instance plugin.
+            #[cfg(desktop)]
+            {
+                let deep_link = app.deep_link();
+                // Installers register the scheme; this covers dev builds and AppImages
+                #[cfg(any(windows, target_os = "linux"))]
+                let _ = deep_link.register_all();
+                let handle = app.handle().clone();
+                deep_link.on_open_url(move |event| {
+                    for url in event.urls() {
+                        deeplink::handle(&handle, url);
+                    }
+                });
+                for url in deep_link.get_current().ok().flatten().unwrap_or_default() {
+                    deeplink::handle(app.handle(), url);
+                }
+            }
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![get_api_port])
-        .run(tauri::generate_context!())
-        .expect("error while running Vimix");
+        .invoke_handler(tauri::generate_handler![
+            get_api_port,
+            get_api_credentials,
+            get_backend_status,
+            restart_backend,
+            get_sidecar_logs,
+            get_toolchain_info,
+            get_settings,
+            save_preset,
+            list_presets,
+            delete_preset,
+            apply_preset,
+            export_presets,
+            import_presets,
+            list_watches,
+            save_watch,
+            remove_watch,
+            save_pipeline,
+            list_pipelines,
+            delete_pipeline,
+            run_pipeline,
+            update_settings,
+            submit_job,
+            rerun_job,
+            track_job,
+            list_active_jobs,
+            list_job_history,
+            get_job_record,
+            delete_job_record,
+            save_job_result,
+            save_batch_results,
+            reveal_result,
+            open_result,
+            take_pending_inputs,
+            get_pending_deep_links,
+            confirm_deep_link,
+            dismiss_deep_link
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while building Vimix")
+        .run(|app, event| match event {
+            // Hold the exit until the sidecar and its children are gone
+            RunEvent::ExitRequested { code, api, .. } if sidecar::begin_shutdown(app) => {
+                api.prevent_exit();
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    sidecar::shutdown(&app).await;
+                    if code == Some(tauri::RESTART_EXIT_CODE) {
+                        app.request_restart();
+                    } else {
+                        app.exit(code.unwrap_or(0));
+                    }
+                });
+            }
+            RunEvent::Exit => sidecar::kill_all(app),
+            // Files opened through a file association on macOS
+            #[cfg(any(target_os = "macos", target_os = "ios"))]
+            RunEvent::Opened { urls } => inputs::open(
+                app,
+                urls.iter()
+                    .filter_map(|url| url.to_file_path().ok())
+                    .collect(),
+            ),
+            _ => {}
+        });
 }
@@ -0,0 +1,275 @@
+//! Sidecar log capture: size-rotated files on disk plus a live in-memory tail.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Rotate `sidecar.log` once it grows past this size.
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Number of rotated files kept next to the live one (`sidecar.log.1` …).
+const MAX_ROTATED_FILES: u32 = 3;
+
+/// Lines kept in memory for `get_sidecar_logs`.
+const RECENT_LINES: usize = 2000;
+
+/// Severity of a sidecar log line, parsed from the Python logging prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl LogLevel {
+    fn parse(prefix: &str) -> Option<Self> {
+        match prefix.trim() {
+            "DEBUG" => Some(Self::Debug),
+            "INFO" => Some(Self::Info),
+            "WARNING" | "WARN" => Some(Self::Warning),
+            "ERROR" | "CRITICAL" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warning => "WARNING",
+            Self::Error => "ERROR",
+        }
+    }
+}
+
+/// Which pipe a line came from.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+impl LogStream {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
+/// One line of sidecar output, as sent in the `sidecar-log` event.
+#[derive(Clone, Debug, Serialize)]
+pub struct LogLine {
+    pub timestamp: String,
+    pub stream: LogStream,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+impl LogLine {
+    /// Classify a raw output line.
+    ///
+    /// Python logging and uvicorn prefix lines with `LEVEL:`; anything
+    /// else (tracebacks, prints) is `info` on stdout and `error` on stderr.
+    fn new(stream: LogStream, raw: &str) -> Self {
+        let parsed = raw
+            .split_once(':')
+            .and_then(|(prefix, rest)| LogLevel::parse(prefix).map(|level| (level, rest.trim())));
+        let (level, message) = match parsed {
+            Some((level, rest)) => (level, rest.to_string()),
+            None => {
+                let level = match stream {
+                    LogStream::Stdout => LogLevel::Info,
+                    LogStream::Stderr => LogLevel::Error,
+                };
+                (level, raw.to_string())
+            }
+        };
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            stream,
+            level,
+            message,
+        }
+    }
+}
+
+/// Append-only log file that rotates itself by size.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > MAX_LOG_BYTES {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{line}")?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Shift `sidecar.log.N` to `.N+1`, dropping the oldest, and start a fresh file.
+    fn rotate(&mut self) -> io::Result<()> {
+        for i in (1..MAX_ROTATED_FILES).rev() {
+            let _ = fs::rename(rotated(&self.path, i), rotated(&self.path, i + 1));
+        }
+        fs::rename(&self.path, rotated(&self.path, 1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn rotated(path: &Path, index: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+struct Inner {
+    recent: VecDeque<LogLine>,
+    file: Option<RotatingFile>,
+}
+
+/// Captured sidecar output, managed as Tauri state.
+pub struct SidecarLogs(Mutex<Inner>);
+
+impl SidecarLogs {
+    /// Open (or create) `sidecar.log` in `dir`.
+    ///
+    /// Logging to disk is best-effort: if the directory is unusable the
+    /// in-memory tail and live events still work.
+    pub fn open(dir: &Path) -> Self {
+        let file = fs::create_dir_all(dir)
+            .and_then(|_| RotatingFile::open(dir.join("sidecar.log")))
+            .ok();
+        Self(Mutex::new(Inner {
+            recent: VecDeque::with_capacity(RECENT_LINES),
+            file,
+        }))
+    }
+
+    fn record(&self, line: &LogLine) {
+        let mut inner = self.0.lock().unwrap();
+        if let Some(file) = inner.file.as_mut() {
+            let _ = file.write_line(&format!(
+                "{} {:<7} [{}] {}",
+                line.timestamp,
+                line.level.as_str(),
+                line.stream.as_str(),
+                line.message
+            ));
+        }
+        if inner.recent.len() == RECENT_LINES {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(line.clone());
+    }
+
+    /// The last `tail` lines at or above `min_level`, oldest first.
+    pub fn tail(&self, tail: usize, min_level: LogLevel) -> Vec<LogLine> {
+        let inner = self.0.lock().unwrap();
+        let mut lines: Vec<LogLine> = inner
+            .recent
+            .iter()
+            .rev()
+            .filter(|line| line.level >= min_level)
+            .take(tail)
+            .cloned()
+            .collect();
+        lines.reverse();
+        lines
+    }
+}
+
+/// Record a chunk of sidecar output and broadcast it as `sidecar-log` events.
+pub fn capture(app: &AppHandle, stream: LogStream, bytes: &[u8]) {
+    let text = String::from_utf8_lossy(bytes);
+    for raw in text.lines() {
+        let raw = raw.trim_end();
+        if raw.is_empty() {
+            continue;
+        }
+        let line = LogLine::new(stream, raw);
+        if let Some(logs) = app.try_state::<SidecarLogs>() {
+            logs.record(&line);
+        }
+        let _ = app.emit("sidecar-log", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_come_from_the_logging_prefix() {
+        let line = LogLine::new(LogStream::Stderr, "WARNING:  Invalid HTTP request");
+        assert_eq!(line.level, LogLevel::Warning);
+        assert_eq!(line.message, "Invalid HTTP request");
+
+        let line = LogLine::new(LogStream::Stdout, "CRITICAL: out of memory");
+        assert_eq!(line.level, LogLevel::Error);
+        assert_eq!(line.message, "out of memory");
+    }
+
+    #[test]
+    fn unprefixed_lines_follow_the_stream() {
+        let line = LogLine::new(LogStream::Stdout, "loading model: u2net");
+        assert_eq!(line.level, LogLevel::Info);
+        assert_eq!(line.message, "loading model: u2net");
+
+        let line = LogLine::new(LogStream::Stderr, "Traceback (most recent call last):");
+        assert_eq!(line.level, LogLevel::Error);
+        assert!(matches!(line.stream, LogStream::Stderr));
+    }
+
+    #[test]
+    fn files_rotate_by_size_and_keep_the_newest() {
+        let dir = std::env::temp_dir().join(format!("vimix-logs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sidecar.log");
+        // Four lines fill a file exactly
+        let line = "x".repeat(MAX_LOG_BYTES as usize / 4 - 1);
+
+        let mut file = RotatingFile::open(path.clone()).unwrap();
+        for _ in 0..4 * (MAX_ROTATED_FILES + 2) {
+            file.write_line(&line).unwrap();
+        }
+        for index in 1..=MAX_ROTATED_FILES {
+            let size = fs::metadata(rotated(&path, index)).unwrap().len();
+            assert_eq!(size, MAX_LOG_BYTES);
+        }
+        assert!(!rotated(&path, MAX_ROTATED_FILES + 1).exists());
+        assert_eq!(fs::metadata(&path).unwrap().len(), MAX_LOG_BYTES);
+
+        // Reopening counts from the current size
+        let mut file = RotatingFile::open(path.clone()).unwrap();
+        file.write_line("y").unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
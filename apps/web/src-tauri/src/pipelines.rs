@@ -0,0 +1,473 @@
+//! Pipelines: an ordered list of processor steps, each one fed the result
+//! of the step before, e.g. trim, then compress, then convert to GIF.
+//!
+//! Pipelines are kept in `pipelines.json` in the app config dir and run
+//! from the app (`run_pipeline`) or the command line (`vimix pipeline`).
+//! Each input file goes through the steps on its own; a failed step stops
+//! that file's run and the steps after it are reported as skipped.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use hyper::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::headless::HeadlessSidecar;
+use crate::jobs::{self, JobInfo, Submitted};
+use crate::json_file;
+use crate::processors::Processor;
+use crate::results::{self, SaveOptions};
+use crate::sidecar;
+
+/// Bumped with every incompatible change to the file format.
+const FILE_VERSION: u32 = 1;
+
+/// How often a running step is polled.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PipelineStep {
+    pub processor_id: String,
+    #[serde(default)]
+    pub options: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Pipeline {
+    /// Empty when adding a pipeline; one is assigned.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub steps: Vec<PipelineStep>,
+}
+
+/// `pipelines.json`.
+#[derive(Deserialize)]
+struct PipelineFile {
+    version: u32,
+    pipelines: Vec<Pipeline>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    /// Not run because an earlier step failed.
+    Skipped,
+}
+
+/// How one step of a run went.
+#[derive(Clone, Debug, Serialize)]
+pub struct StepReport {
+    pub processor_id: String,
+    /// The sidecar job, once submitted.
+    pub job_id: Option<String>,
+    pub status: StepStatus,
+    pub progress: f64,
+    pub message: String,
+    pub error: Option<String>,
+    pub duration_ms: Option<u64>,
+}
+
+/// One input file going through a pipeline; the payload of
+/// `pipeline-progress`.
+#[derive(Clone, Debug, Serialize)]
+pub struct PipelineRun {
+    pub id: String,
+    pub pipeline: String,
+    pub input: PathBuf,
+    /// `running`, `completed` or `failed`.
+    pub status: String,
+    /// Over all steps, 0 to 100.
+    pub progress: f64,
+    pub steps: Vec<StepReport>,
+    /// Where the last step's result was saved.
+    pub output: Option<PathBuf>,
+    /// Why the run failed outside of a step, e.g. saving the result.
+    pub error: Option<String>,
+}
+
+impl PipelineRun {
+    fn update_progress(&mut self) {
+        let done: f64 = self.steps.iter().map(|step| step.progress).sum();
+        self.progress = done / self.steps.len().max(1) as f64;
+    }
+}
+
+/// Pipelines loaded from disk, managed as Tauri state and read by the
+/// command line.
+pub struct PipelineStore {
+    path: PathBuf,
+    pipelines: Mutex<Vec<Pipeline>>,
+}
+
+impl PipelineStore {
+    /// Load `pipelines.json` from `dir`.
+    ///
+    /// A missing file means no pipelines. An unreadable one is moved aside
+    /// to `pipelines.json.bak` so the next save doesn't silently replace it.
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join("pipelines.json");
+        let pipelines = json_file::load(&path, parse).unwrap_or_default();
+        Self {
+            path,
+            pipelines: Mutex::new(pipelines),
+        }
+    }
+
+    /// Load `pipelines.json` from `dir` without touching it, for the
+    /// command line: an unreadable file is an error and stays where it is,
+    /// since the app may be running and own it.
+    pub fn read(dir: &Path) -> Result<Self, String> {
+        let path = dir.join("pipelines.json");
+        let pipelines = json_file::read(&path, parse)?.unwrap_or_default();
+        Ok(Self {
+            path,
+            pipelines: Mutex::new(pipelines),
+        })
+    }
+
+    /// All pipelines, by name.
+    pub fn list(&self) -> Vec<Pipeline> {
+        let mut pipelines = self.pipelines.lock().unwrap().clone();
+        pipelines.sort_by_key(|pipeline| pipeline.name.to_lowercase());
+        pipelines
+    }
+
+    /// A pipeline by id, or by name (case-insensitive).
+    pub fn find(&self, id_or_name: &str) -> Option<Pipeline> {
+        let pipelines = self.pipelines.lock().unwrap();
+        pipelines
+            .iter()
+            .find(|pipeline| pipeline.id == id_or_name)
+            .or_else(|| {
+                pipelines
+                    .iter()
+                    .find(|pipeline| pipeline.name.eq_ignore_ascii_case(id_or_name))
+            })
+            .cloned()
+    }
+
+    /// Add or replace a pipeline, by id or else by name.
+    fn put(&self, mut pipeline: Pipeline) -> Result<Pipeline, String> {
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let mut updated = pipelines.clone();
+        let existing = updated
+            .iter()
+            .position(|p| p.id == pipeline.id)
+            .or_else(|| {
+                updated
+                    .iter()
+                    .position(|p| p.name.to_lowercase() == pipeline.name.to_lowercase())
+            });
+        match existing {
+            Some(index) => {
+                pipeline.id = updated[index].id.clone();
+                updated[index] = pipeline.clone();
+            }
+            None => {
+                pipeline.id = uuid::Uuid::new_v4().to_string();
+                updated.push(pipeline.clone());
+            }
+        }
+        self.save(&updated)?;
+        *pipelines = updated;
+        Ok(pipeline)
+    }
+
+    /// Remove a pipeline. Returns `false` if there was none.
+    pub fn delete(&self, id: &str) -> Result<bool, String> {
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let Some(index) = pipelines.iter().position(|pipeline| pipeline.id == id) else {
+            return Ok(false);
+        };
+        let mut updated = pipelines.clone();
+        updated.remove(index);
+        self.save(&updated)?;
+        *pipelines = updated;
+        Ok(true)
+    }
+
+    fn save(&self, pipelines: &[Pipeline]) -> Result<(), String> {
+        json_file::write(
+            &self.path,
+            &serde_json::json!({
+                "version": FILE_VERSION,
+                "pipelines": pipelines,
+            }),
+        )
+    }
+}
+
+fn parse(text: &str) -> Result<Vec<Pipeline>, String> {
+    let file: PipelineFile =
+        serde_json::from_str(text).map_err(|e| format!("Invalid pipelines file: {e}"))?;
+    if file.version > FILE_VERSION {
+        return Err(format!(
+            "Pipelines file was written by a newer Vimix (version {})",
+            file.version
+        ));
+    }
+    Ok(file.pipelines)
+}
+
+/// Where the steps run: the app's sidecar, or a headless one for the
+/// command line.
+#[derive(Clone, Copy)]
+pub enum Engine<'a> {
+    App(&'a AppHandle),
+    Headless(&'a HeadlessSidecar),
+}
+
+impl Engine<'_> {
+    async fn processors(&self) -> Result<Vec<Processor>, String> {
+        match self {
+            Self::App(app) => sidecar::call(app, Method::GET, "/processors", None).await,
+            Self::Headless(sidecar) => sidecar.call(Method::GET, "/processors", None).await,
+        }
+    }
+
+    /// Submit one file and return the job id. In the app, the job is
+    /// recorded in the history and followed like any other.
+    async fn submit(&self, step: &PipelineStep, path: &Path) -> Result<String, String> {
+        let submitted = match self {
+            Self::App(app) => {
+                jobs::submit(
+                    app,
+                    step.processor_id.clone(),
+                    vec![path.to_path_buf()],
+                    Value::Object(step.options.clone()),
+                )
+                .await?
+            }
+            Self::Headless(sidecar) => {
+                let path = path
+                    .canonicalize()
+                    .map_err(|e| format!("{}: {e}", path.display()))?;
+                let body = serde_json::json!({
+                    "processor_id": step.processor_id,
+                    "paths": [path],
+                    "options": step.options,
+                });
+                sidecar
+                    .call::<Submitted>(Method::POST, "/jobs/local", Some(&body))
+                    .await?
+            }
+        };
+        match submitted {
+            Submitted::Job { id } => Ok(id),
+            Submitted::Batch { .. } => Err("Expected a single job".to_string()),
+        }
+    }
+
+    async fn fetch(&self, job_id: &str) -> Result<JobInfo, String> {
+        match self {
+            Self::App(app) => jobs::fetch(app, job_id).await,
+            Self::Headless(sidecar) => {
+                sidecar
+                    .call(Method::GET, &format!("/jobs/{job_id}"), None)
+                    .await
+            }
+        }
+    }
+
+    /// Poll a job until it completes or fails and return it as it ended,
+    /// passing every unfinished state to `on_progress` on the way.
+    pub async fn wait_for_job(
+        &self,
+        job_id: &str,
+        mut on_progress: impl FnMut(&JobInfo),
+    ) -> Result<JobInfo, String> {
+        loop {
+            let job = self.fetch(job_id).await?;
+            if matches!(job.status.as_str(), "completed" | "failed") {
+                return Ok(job);
+            }
+            on_progress(&job);
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    fn result_file(&self, job: &JobInfo) -> Result<PathBuf, String> {
+        match self {
+            Self::App(app) => jobs::result_file(app, job),
+            Self::Headless(sidecar) => jobs::result_in(&sidecar.jobs_dir(), job),
+        }
+    }
+}
+
+/// Check that a pipeline can run: it has a name and steps, and every step's
+/// processor exists and takes its options. Returns it with the options
+/// converted like the frontend would send them.
+pub async fn check(engine: Engine<'_>, mut pipeline: Pipeline) -> Result<Pipeline, String> {
+    pipeline.name = pipeline.name.trim().to_string();
+    if pipeline.name.is_empty() {
+        return Err("Pipeline name is empty".to_string());
+    }
+    if pipeline.steps.is_empty() {
+        return Err(format!("Pipeline {} has no steps", pipeline.name));
+    }
+    let processors = engine.processors().await?;
+    for (index, step) in pipeline.steps.iter_mut().enumerate() {
+        let processor = processors
+            .iter()
+            .find(|processor| processor.id == step.processor_id)
+            .ok_or_else(|| {
+                format!(
+                    "Step {}: unknown processor {}",
+                    index + 1,
+                    step.processor_id
+                )
+            })?;
+        step.options = processor
+            .check_options(&step.options)
+            .map_err(|e| format!("Step {} ({}): {e}", index + 1, processor.id))?;
+    }
+    Ok(pipeline)
+}
+
+/// Validate and store a pipeline. Saving under an existing name replaces
+/// that pipeline.
+pub async fn save(app: &AppHandle, pipeline: Pipeline) -> Result<Pipeline, String> {
+    let pipeline = check(Engine::App(app), pipeline).await?;
+    app.state::<PipelineStore>().put(pipeline)
+}
+
+/// Run `input` through every step of `pipeline` and save the last result
+/// into `dest_dir`. `on_update` is called whenever the run changes.
+///
+/// `index` is what `{index}` stands for in the naming template; `{stem}`
+/// and `{name}` are those of `input`, `{processor}` the last step's.
+///
+/// The pipeline is expected to have been `check`ed.
+pub async fn run(
+    engine: Engine<'_>,
+    pipeline: &Pipeline,
+    input: &Path,
+    dest_dir: &Path,
+    save_options: &SaveOptions,
+    index: Option<usize>,
+    mut on_update: impl FnMut(&PipelineRun),
+) -> PipelineRun {
+    let mut run = PipelineRun {
+        id: uuid::Uuid::new_v4().to_string(),
+        pipeline: pipeline.name.clone(),
+        input: input.to_path_buf(),
+        status: "running".to_string(),
+        progress: 0.0,
+        steps: pipeline
+            .steps
+            .iter()
+            .map(|step| StepReport {
+                processor_id: step.processor_id.clone(),
+                job_id: None,
+                status: StepStatus::Pending,
+                progress: 0.0,
+                message: String::new(),
+                error: None,
+                duration_ms: None,
+            })
+            .collect(),
+        output: None,
+        error: None,
+    };
+    on_update(&run);
+
+    let mut current = input.to_path_buf();
+    let mut last_job = None;
+    for (index, step) in pipeline.steps.iter().enumerate() {
+        let started = Instant::now();
+        run.steps[index].status = StepStatus::Running;
+        on_update(&run);
+
+        let outcome = run_step(engine, step, &current, &mut run, index, &mut on_update).await;
+        let report = &mut run.steps[index];
+        report.duration_ms = Some(started.elapsed().as_millis() as u64);
+        match outcome {
+            Ok((job, result)) => {
+                report.status = StepStatus::Completed;
+                report.progress = 100.0;
+                current = result;
+                last_job = Some(job);
+            }
+            Err(reason) => {
+                report.status = StepStatus::Failed;
+                report.error = Some(reason);
+                for later in &mut run.steps[index + 1..] {
+                    later.status = StepStatus::Skipped;
+                }
+                run.status = "failed".to_string();
+                on_update(&run);
+                return run;
+            }
+        }
+        run.update_progress();
+        on_update(&run);
+    }
+
+    // Named after the pipeline's input, not the intermediate file
+    let mut job = last_job.expect("pipelines have at least one step");
+    job.original_filename = input
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or(job.original_filename);
+    match results::save(&job, &current, dest_dir, save_options, index) {
+        Ok(saved) => {
+            if let Engine::App(app) = engine {
+                results::record(app, &saved);
+            }
+            match saved.path {
+                Some(path) => {
+                    run.output = Some(path);
+                    run.status = "completed".to_string();
+                }
+                None => {
+                    run.error = saved.skipped;
+                    run.status = "failed".to_string();
+                }
+            }
+        }
+        Err(reason) => {
+            run.error = Some(reason);
+            run.status = "failed".to_string();
+        }
+    }
+    on_update(&run);
+    run
+}
+
+/// Submit one step and wait for it, reporting its progress. Returns the
+/// finished job and its result file.
+async fn run_step(
+    engine: Engine<'_>,
+    step: &PipelineStep,
+    input: &Path,
+    run: &mut PipelineRun,
+    index: usize,
+    on_update: &mut impl FnMut(&PipelineRun),
+) -> Result<(JobInfo, PathBuf), String> {
+    let job_id = engine.submit(step, input).await?;
+    run.steps[index].job_id = Some(job_id.clone());
+    let job = engine
+        .wait_for_job(&job_id, |job| {
+            let report = &mut run.steps[index];
+            if report.progress != job.progress || report.message != job.message {
+                report.progress = job.progress;
+                report.message = job.message.clone();
+                run.update_progress();
+                on_update(run);
+            }
+        })
+        .await?;
+    if job.status == "failed" {
+        return Err(job.error.unwrap_or_else(|| "Job failed".to_string()));
+    }
+    let result = engine.result_file(&job)?;
+    Ok((job, result))
+}
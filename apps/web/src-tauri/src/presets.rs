@@ -0,0 +1,253 @@
+//! Named option presets per processor ("web GIF 480px 12fps"), kept in
+//! `presets.json` in the app config dir.
+//!
+//! Options are checked against the processor's schema when a preset is
+//! saved or imported, and again when it is applied, since the schema may
+//! have changed in between. Exported files use the same format as
+//! `presets.json`, so a team can share house presets.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::{json_file, processors};
+
+/// Bumped with every incompatible change to the file format.
+const FILE_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Preset {
+    pub id: String,
+    pub name: String,
+    pub processor_id: String,
+    pub options: Map<String, Value>,
+}
+
+/// `presets.json`, and exported presets.
+#[derive(Deserialize)]
+struct PresetFile {
+    version: u32,
+    presets: Vec<Preset>,
+}
+
+/// A preset's options as they apply to the processor today.
+#[derive(Debug, Serialize)]
+pub struct AppliedPreset {
+    pub processor_id: String,
+    pub options: Map<String, Value>,
+    /// Why options were left out: removed from the processor, or no
+    /// longer valid values.
+    pub skipped: Vec<String>,
+}
+
+/// Presets loaded from disk, managed as Tauri state.
+pub struct PresetStore {
+    path: PathBuf,
+    presets: Mutex<Vec<Preset>>,
+}
+
+impl PresetStore {
+    /// Load `presets.json` from `dir`.
+    ///
+    /// A missing file means no presets. An unreadable one is moved aside
+    /// to `presets.json.bak` so the next save doesn't silently replace it.
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join("presets.json");
+        let presets = json_file::load(&path, parse).unwrap_or_default();
+        Self {
+            path,
+            presets: Mutex::new(presets),
+        }
+    }
+
+    /// All presets, or those of one processor, by name.
+    pub fn list(&self, processor_id: Option<&str>) -> Vec<Preset> {
+        let mut presets: Vec<Preset> = self
+            .presets
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|preset| match processor_id {
+                Some(id) => preset.processor_id == id,
+                None => true,
+            })
+            .cloned()
+            .collect();
+        presets.sort_by_key(|preset| preset.name.to_lowercase());
+        presets
+    }
+
+    pub fn get(&self, id: &str) -> Option<Preset> {
+        self.presets
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|preset| preset.id == id)
+            .cloned()
+    }
+
+    /// Add `new` presets, replacing any with the same id, or the same name
+    /// for the same processor (keeping its id). Returns them as stored.
+    fn put(&self, new: Vec<Preset>) -> Result<Vec<Preset>, String> {
+        let mut presets = self.presets.lock().unwrap();
+        let mut updated = presets.clone();
+        let mut stored = Vec::with_capacity(new.len());
+        for mut preset in new {
+            let existing = updated.iter().position(|p| p.id == preset.id).or_else(|| {
+                updated.iter().position(|p| {
+                    p.processor_id == preset.processor_id
+                        && p.name.to_lowercase() == preset.name.to_lowercase()
+                })
+            });
+            match existing {
+                Some(index) => {
+                    preset.id = updated[index].id.clone();
+                    updated[index] = preset.clone();
+                }
+                None => updated.push(preset.clone()),
+            }
+            stored.push(preset);
+        }
+        self.save(&updated)?;
+        *presets = updated;
+        Ok(stored)
+    }
+
+    /// Remove a preset. Returns `false` if there was none.
+    pub fn delete(&self, id: &str) -> Result<bool, String> {
+        let mut presets = self.presets.lock().unwrap();
+        let Some(index) = presets.iter().position(|preset| preset.id == id) else {
+            return Ok(false);
+        };
+        let mut updated = presets.clone();
+        updated.remove(index);
+        self.save(&updated)?;
+        *presets = updated;
+        Ok(true)
+    }
+
+    /// Write presets `ids` (all of them if empty) to `path`.
+    pub fn export(&self, ids: &[String], path: &Path) -> Result<usize, String> {
+        let presets: Vec<Preset> = self
+            .list(None)
+            .into_iter()
+            .filter(|preset| ids.is_empty() || ids.contains(&preset.id))
+            .collect();
+        write(path, &presets)?;
+        Ok(presets.len())
+    }
+
+    fn save(&self, presets: &[Preset]) -> Result<(), String> {
+        write(&self.path, presets)
+    }
+}
+
+/// Validate a preset against its processor and store it. Saving under an
+/// existing name for the same processor replaces that preset.
+pub async fn save(
+    app: &AppHandle,
+    id: Option<String>,
+    name: String,
+    processor_id: String,
+    options: Map<String, Value>,
+) -> Result<Preset, String> {
+    let preset = check(
+        app,
+        Preset {
+            id: id.unwrap_or_else(new_id),
+            name,
+            processor_id,
+            options,
+        },
+    )
+    .await?;
+    let mut stored = app.state::<PresetStore>().put(vec![preset])?;
+    Ok(stored.remove(0))
+}
+
+/// A preset's options, checked against the processor's current schema.
+pub async fn apply(app: &AppHandle, id: &str) -> Result<AppliedPreset, String> {
+    let preset = app
+        .state::<PresetStore>()
+        .get(id)
+        .ok_or_else(|| format!("No preset {id}"))?;
+    let processor = processors::fetch(app, &preset.processor_id).await?;
+    let mut options = Map::new();
+    let mut skipped = Vec::new();
+    for (key, value) in preset.options {
+        match processor.check_option(&key, &value) {
+            Ok(value) => {
+                options.insert(key, value);
+            }
+            Err(reason) => skipped.push(reason),
+        }
+    }
+    Ok(AppliedPreset {
+        processor_id: preset.processor_id,
+        options,
+        skipped,
+    })
+}
+
+/// Read presets exported to `path` and store them. All of them must be
+/// valid; on a name clash the imported preset wins.
+pub async fn import(app: &AppHandle, path: &Path) -> Result<Vec<Preset>, String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let mut presets = Vec::new();
+    for preset in parse(&text)? {
+        let preset = Preset {
+            // Ids are local to each install
+            id: new_id(),
+            ..preset
+        };
+        presets.push(check(app, preset).await?);
+    }
+    app.state::<PresetStore>().put(presets)
+}
+
+async fn check(app: &AppHandle, preset: Preset) -> Result<Preset, String> {
+    let name = preset.name.trim().to_string();
+    if name.is_empty() {
+        return Err("Preset name is empty".to_string());
+    }
+    let processor = processors::fetch(app, &preset.processor_id).await?;
+    let options = processor
+        .check_options(&preset.options)
+        .map_err(|e| format!("Preset {name}: {e}"))?;
+    Ok(Preset {
+        name,
+        options,
+        ..preset
+    })
+}
+
+fn parse(text: &str) -> Result<Vec<Preset>, String> {
+    let file: PresetFile =
+        serde_json::from_str(text).map_err(|e| format!("Invalid presets file: {e}"))?;
+    if file.version > FILE_VERSION {
+        return Err(format!(
+            "Presets file was written by a newer Vimix (version {})",
+            file.version
+        ));
+    }
+    Ok(file.presets)
+}
+
+fn write(path: &Path, presets: &[Preset]) -> Result<(), String> {
+    json_file::write(
+        path,
+        &serde_json::json!({
+            "version": FILE_VERSION,
+            "presets": presets,
+        }),
+    )
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
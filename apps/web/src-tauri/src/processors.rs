@@ -0,0 +1,212 @@
+//! Processor descriptions served by the sidecar's `/processors` endpoint,
+//! and checking user-supplied options against them.
+
+use std::path::Path;
+
+use hyper::Method;
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+use tauri::AppHandle;
+
+use crate::sidecar;
+
+/// One `/processors` entry.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Processor {
+    pub id: String,
+    pub label: String,
+    pub accepted_extensions: Vec<String>,
+    #[serde(default)]
+    pub options_schema: Vec<OptionSchema>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OptionSchema {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub choices: Vec<Choice>,
+    #[serde(default)]
+    pub allow_original: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Choice {
+    pub value: String,
+}
+
+impl Processor {
+    /// Whether the processor takes files with `path`'s extension.
+    pub fn accepts(&self, path: &Path) -> bool {
+        path.extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy().to_lowercase()))
+            .is_some_and(|ext| self.accepted_extensions.contains(&ext))
+    }
+
+    /// Convert a textual option value (from a link or the command line)
+    /// to what the frontend would send for it.
+    pub fn option_value(&self, id: &str, raw: &str) -> Result<Value, String> {
+        let schema = self
+            .options_schema
+            .iter()
+            .find(|schema| schema.id == id)
+            .ok_or_else(|| format!("{} has no option {id}", self.id))?;
+        match schema.kind.as_str() {
+            "number" => {
+                let number: f64 = raw
+                    .parse()
+                    .map_err(|_| format!("{id} must be a number, got {raw}"))?;
+                schema.check_range(number)?;
+                let number = if number.fract() == 0.0 {
+                    Number::from(number as i64)
+                } else {
+                    Number::from_f64(number).ok_or_else(|| format!("{id} must be finite"))?
+                };
+                Ok(Value::Number(number))
+            }
+            "dimension" => {
+                if raw == "original" && schema.allow_original {
+                    return Ok(Value::String(raw.to_string()));
+                }
+                let pixels: u32 = raw
+                    .parse()
+                    .map_err(|_| format!("{id} must be a size in pixels, got {raw}"))?;
+                schema.check_range(f64::from(pixels))?;
+                Ok(Value::String(pixels.to_string()))
+            }
+            "select" => {
+                if !schema.choices.iter().any(|choice| choice.value == raw) {
+                    let choices: Vec<_> = schema.choices.iter().map(|c| c.value.as_str()).collect();
+                    return Err(format!(
+                        "{id} must be one of {}, got {raw}",
+                        choices.join(", ")
+                    ));
+                }
+                Ok(Value::String(raw.to_string()))
+            }
+            "text" => Ok(Value::String(raw.to_string())),
+            other => Err(format!("Option {id} has unsupported type {other}")),
+        }
+    }
+
+    /// Check options given as JSON (presets, imported files) and convert
+    /// them like `option_value`.
+    pub fn check_options(
+        &self,
+        options: &Map<String, Value>,
+    ) -> Result<Map<String, Value>, String> {
+        options
+            .iter()
+            .map(|(id, value)| Ok((id.clone(), self.check_option(id, value)?)))
+            .collect()
+    }
+
+    /// Same as `check_options`, for one option.
+    pub fn check_option(&self, id: &str, value: &Value) -> Result<Value, String> {
+        match value {
+            Value::String(text) => self.option_value(id, text),
+            Value::Number(number) => self.option_value(id, &number.to_string()),
+            other => Err(format!("{id} must be a string or a number, got {other}")),
+        }
+    }
+}
+
+/// Look up one processor on the running sidecar.
+pub async fn fetch(app: &AppHandle, id: &str) -> Result<Processor, String> {
+    let processors: Vec<Processor> = sidecar::call(app, Method::GET, "/processors", None).await?;
+    find(processors, id)
+}
+
+fn find(processors: Vec<Processor>, id: &str) -> Result<Processor, String> {
+    processors
+        .into_iter()
+        .find(|processor| processor.id == id)
+        .ok_or_else(|| format!("Unknown processor {id}"))
+}
+
+impl OptionSchema {
+    fn check_range(&self, value: f64) -> Result<(), String> {
+        let id = &self.id;
+        if !value.is_finite() {
+            return Err(format!("{id} must be finite"));
+        }
+        if let Some(min) = self.min.filter(|min| value < *min) {
+            return Err(format!("{id} must be at least {min}, got {value}"));
+        }
+        if let Some(max) = self.max.filter(|max| value > *max) {
+            return Err(format!("{id} must be at most {max}, got {value}"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processor() -> Processor {
+        serde_json::from_value(serde_json::json!({
+            "id": "image-compress",
+            "label": "Compress images",
+            "accepted_extensions": [".png", ".jpg"],
+            "options_schema": [
+                {"id": "quality", "type": "number", "min": 1, "max": 100},
+                {"id": "width", "type": "dimension", "allow_original": true},
+                {"id": "format", "type": "select", "choices": [{"value": "webp"}, {"value": "png"}]},
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn option_values_follow_the_schema() {
+        let processor = processor();
+        assert_eq!(
+            processor.option_value("quality", "72.5"),
+            Ok(serde_json::json!(72.5))
+        );
+        assert!(processor.option_value("quality", "0").is_err());
+        assert_eq!(
+            processor.option_value("width", "640"),
+            Ok(serde_json::json!("640"))
+        );
+        assert!(processor.option_value("width", "-640").is_err());
+        assert_eq!(
+            processor.option_value("format", "webp"),
+            Ok(serde_json::json!("webp"))
+        );
+        assert_eq!(
+            processor.option_value("format", "gif").unwrap_err(),
+            "format must be one of webp, png, got gif"
+        );
+        assert_eq!(
+            processor.option_value("speed", "2").unwrap_err(),
+            "image-compress has no option speed"
+        );
+    }
+
+    #[test]
+    fn processors_are_found_by_id() {
+        assert_eq!(
+            find(vec![processor()], "image-compress").unwrap().label,
+            "Compress images"
+        );
+        assert_eq!(
+            find(vec![processor()], "video-trim").unwrap_err(),
+            "Unknown processor video-trim"
+        );
+    }
+
+    #[test]
+    fn extensions_are_matched_case_insensitively() {
+        let processor = processor();
+        assert!(processor.accepts(Path::new("/photos/a.PNG")));
+        assert!(!processor.accepts(Path::new("/photos/a.gif")));
+        assert!(!processor.accepts(Path::new("/photos/png")));
+    }
+}
@@ -0,0 +1,243 @@
+//! Job progress, followed once per job by the app and broadcast to every
+//! view.
+//!
+//! Each tracked job gets a single subscription to the sidecar's
+//! `/jobs/{id}/progress` stream, however many pages show it. Updates go
+//! out as `job-progress` events and the final state as `job-finished`;
+//! `list_active_jobs` returns the latest state of the unfinished ones.
+//! Every followed job is kept in the `history`.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::Request;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::client;
+use crate::history::JobHistory;
+use crate::jobs;
+use crate::sidecar::{self, ApiToken, TOKEN_HEADER};
+
+/// How long to wait before following a job again after its stream ended
+/// early (the sidecar's idle timeout, or a lost connection).
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Latest known state of a job, the payload of `job-progress` and
+/// `job-finished`.
+#[derive(Clone, Debug, Serialize)]
+pub struct JobProgress {
+    pub id: String,
+    /// `None` until the sidecar's first event.
+    pub processor_id: Option<String>,
+    pub original_filename: Option<String>,
+    /// `pending`, `processing`, `completed` or `failed`.
+    pub status: String,
+    pub progress: f64,
+    /// Progress message; for a failed job, the error.
+    pub message: String,
+}
+
+impl JobProgress {
+    fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "completed" | "failed")
+    }
+
+    fn apply(&mut self, update: Update) {
+        if update.processor_id.is_some() {
+            self.processor_id = update.processor_id;
+        }
+        if update.original_filename.is_some() {
+            self.original_filename = update.original_filename;
+        }
+        self.status = update.status;
+        if let Some(progress) = update.progress {
+            self.progress = progress;
+        }
+        if let Some(message) = update.error.or(update.message) {
+            self.message = message;
+        }
+    }
+}
+
+/// One event of the sidecar's progress stream. The first is the whole job,
+/// later ones only carry `status`, `progress` and `message`.
+#[derive(Deserialize)]
+struct Update {
+    status: String,
+    #[serde(default)]
+    progress: Option<f64>,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    processor_id: Option<String>,
+    #[serde(default)]
+    original_filename: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    options: Option<Value>,
+}
+
+/// Jobs being followed, in the order they were started.
+#[derive(Default)]
+pub struct ActiveJobs(Mutex<Vec<JobProgress>>);
+
+/// Start following `job_id` unless it is already followed.
+pub fn track(app: &AppHandle, job_id: String) {
+    {
+        let state = app.state::<ActiveJobs>();
+        let mut active = state.0.lock().unwrap();
+        if active.iter().any(|job| job.id == job_id) {
+            return;
+        }
+        active.push(JobProgress {
+            id: job_id.clone(),
+            processor_id: None,
+            original_filename: None,
+            status: "pending".to_string(),
+            progress: 0.0,
+            message: String::new(),
+        });
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move { follow(&app, &job_id).await });
+}
+
+/// Follow every job `submitted` created.
+pub fn track_submitted(app: &AppHandle, submitted: &jobs::Submitted) {
+    match submitted {
+        jobs::Submitted::Job { id } => track(app, id.clone()),
+        jobs::Submitted::Batch { job_ids, .. } => {
+            for id in job_ids {
+                track(app, id.clone());
+            }
+        }
+    }
+}
+
+/// The jobs that haven't finished yet.
+pub fn active(app: &AppHandle) -> Vec<JobProgress> {
+    app.state::<ActiveJobs>().0.lock().unwrap().clone()
+}
+
+async fn follow(app: &AppHandle, job_id: &str) {
+    loop {
+        if let Ok(true) = stream(app, job_id).await {
+            return;
+        }
+        // The stream ended before the job did: check where it stands
+        match jobs::fetch(app, job_id).await {
+            Ok(job) => {
+                let finished = update(
+                    app,
+                    job_id,
+                    Update {
+                        status: job.status,
+                        progress: Some(job.progress),
+                        message: Some(job.message),
+                        processor_id: Some(job.processor_id),
+                        original_filename: Some(job.original_filename),
+                        error: job.error,
+                        options: Some(job.options),
+                    },
+                );
+                if finished {
+                    return;
+                }
+            }
+            Err(reason) => {
+                // Gone with a restarted sidecar, or never existed
+                update(
+                    app,
+                    job_id,
+                    Update {
+                        status: "failed".to_string(),
+                        progress: None,
+                        message: None,
+                        processor_id: None,
+                        original_filename: None,
+                        error: Some(reason),
+                        options: None,
+                    },
+                );
+                return;
+            }
+        }
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+/// Read the job's progress stream until it ends. Returns whether the job
+/// finished.
+async fn stream(app: &AppHandle, job_id: &str) -> Result<bool, String> {
+    let endpoint = sidecar::endpoint(app).ok_or("Backend is not ready")?;
+    let req = Request::get(format!("/jobs/{job_id}/progress"))
+        .header(TOKEN_HEADER, &app.state::<ApiToken>().0)
+        .body(Full::new(Bytes::new()))
+        .map_err(|e| e.to_string())?;
+    let response = client::send(&endpoint, req).await?;
+    if !response.status().is_success() {
+        return Err(format!("Sidecar returned {}", response.status()));
+    }
+
+    let mut body = response.into_body();
+    let mut buffer = String::new();
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(|e| format!("Progress stream failed: {e}"))?;
+        let Ok(data) = frame.into_data() else {
+            continue;
+        };
+        buffer.push_str(&String::from_utf8_lossy(&data));
+        while let Some(end) = buffer.find("\n\n") {
+            let event: String = buffer.drain(..end + 2).collect();
+            for json in event.lines().filter_map(|line| line.strip_prefix("data: ")) {
+                let Ok(event) = serde_json::from_str::<Update>(json) else {
+                    continue;
+                };
+                // Sent after a minute without updates; the job may still be running
+                if event.status == "timeout" {
+                    return Ok(false);
+                }
+                if update(app, job_id, event) {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Record an update and broadcast it. Returns whether the job finished,
+/// in which case it is no longer active.
+fn update(app: &AppHandle, job_id: &str, mut update: Update) -> bool {
+    let state = app.state::<ActiveJobs>();
+    let mut active = state.0.lock().unwrap();
+    let Some(index) = active.iter().position(|job| job.id == job_id) else {
+        return true;
+    };
+    // Only the first event of a stream carries the options
+    let options = update.options.take();
+    let changed = options.is_some() || active[index].status != update.status;
+    active[index].apply(update);
+    let job = active[index].clone();
+    let finished = job.is_finished();
+    if finished {
+        active.remove(index);
+    }
+    drop(active);
+
+    // Progress alone isn't worth a database write
+    if changed {
+        let _ = app.state::<JobHistory>().observe(&job, options.as_ref());
+    }
+    if finished {
+        let _ = app.emit("job-finished", job);
+    } else {
+        let _ = app.emit("job-progress", job);
+    }
+    finished
+}
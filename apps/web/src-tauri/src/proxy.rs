@@ -0,0 +1,185 @@
+//! `vimix-api://` URI scheme that forwards webview requests to the sidecar.
+//!
+//! With the Unix socket transport the webview can't reach the sidecar
+//! directly, so it fetches `vimix-api://localhost/<path>` instead and the
+//! request is replayed here over the socket, with the API token added.
+//!
+//! Tauri takes a custom protocol response in one piece, so bodies can't be
+//! streamed through. Two kinds of responses would otherwise be held back
+//! until the sidecar is done with them:
+//!
+//! - progress streams (`text/event-stream`) are answered with their first
+//!   event, the job's current state, and a `retry` hint, so `EventSource`
+//!   reconnects shortly for the next one instead of waiting for the job;
+//! - range requests, as sent by `<video>` and `<audio>`, get at most
+//!   `MAX_RANGE` bytes each, so a large result plays without being loaded
+//!   whole.
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use tauri::http::header::{
+    HeaderMap, HeaderValue, CONNECTION, CONTENT_LENGTH, CONTENT_TYPE, HOST, RANGE,
+    TRANSFER_ENCODING,
+};
+use tauri::http::{Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+
+use crate::client;
+use crate::sidecar::{self, ApiToken, TOKEN_HEADER};
+
+/// Most bytes returned for one range request.
+const MAX_RANGE: u64 = 8 * 1024 * 1024;
+
+/// How soon, in milliseconds, `EventSource` asks for the next progress event.
+const SSE_RETRY_MS: u32 = 500;
+
+/// Forward one webview request to the sidecar and return its response.
+pub async fn forward(app: &AppHandle, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+    match try_forward(app, request).await {
+        Ok(response) => response,
+        Err(detail) => Response::builder()
+            .status(StatusCode::BAD_GATEWAY)
+            .header(CONTENT_TYPE, "application/json")
+            .header("Access-Control-Allow-Origin", "*")
+            .body(
+                serde_json::json!({ "detail": detail })
+                    .to_string()
+                    .into_bytes(),
+            )
+            .expect("valid error response"),
+    }
+}
+
+async fn try_forward(
+    app: &AppHandle,
+    request: Request<Vec<u8>>,
+) -> Result<Response<Vec<u8>>, String> {
+    let endpoint = sidecar::endpoint(app).ok_or("Backend is not ready")?;
+
+    let (parts, body) = request.into_parts();
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/")
+        .parse()
+        .map_err(|e| format!("Invalid request path: {e}"))?;
+
+    let mut req = Request::from_parts(parts, Full::new(Bytes::from(body)));
+    *req.uri_mut() = path;
+    let headers = req.headers_mut();
+    headers.remove(HOST);
+    headers.insert(
+        TOKEN_HEADER,
+        app.state::<ApiToken>()
+            .0
+            .parse()
+            .map_err(|_| "Invalid API token")?,
+    );
+    limit_range(headers);
+
+    let (mut parts, body) = client::send(&endpoint, req).await?.into_parts();
+    let is_event_stream = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"));
+    let body = if is_event_stream {
+        first_event(body).await?
+    } else {
+        body.collect()
+            .await
+            .map_err(|e| format!("Failed to read sidecar response: {e}"))?
+            .to_bytes()
+            .into()
+    };
+
+    // The body has been buffered, so hop-by-hop framing no longer applies.
+    parts.headers.remove(TRANSFER_ENCODING);
+    parts.headers.remove(CONNECTION);
+    parts
+        .headers
+        .insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+    Ok(Response::from_parts(parts, body))
+}
+
+/// Cap a `Range: bytes=start-[end]` request at `MAX_RANGE` bytes. The
+/// sidecar then answers with a shorter `Content-Range`, which media
+/// elements follow up on by themselves.
+fn limit_range(headers: &mut HeaderMap) {
+    let Some(limited) = headers
+        .get(RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(limited_range)
+    else {
+        return;
+    };
+    if let Ok(value) = HeaderValue::from_str(&limited) {
+        headers.insert(RANGE, value);
+    }
+}
+
+/// The capped form of `range`, or `None` to forward it as it is
+/// (already small enough, a suffix or multiple ranges, or invalid).
+fn limited_range(range: &str) -> Option<String> {
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let start: u64 = start.trim().parse().ok()?;
+    let last = start.saturating_add(MAX_RANGE - 1);
+    match end.trim() {
+        "" => Some(format!("bytes={start}-{last}")),
+        end => {
+            let end: u64 = end.parse().ok()?;
+            (end > last).then(|| format!("bytes={start}-{last}"))
+        }
+    }
+}
+
+/// Read a progress stream up to its first event and hang up.
+async fn first_event(mut body: Incoming) -> Result<Vec<u8>, String> {
+    let mut event = format!("retry: {SSE_RETRY_MS}\n").into_bytes();
+    let header = event.len();
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(|e| format!("Progress stream failed: {e}"))?;
+        if let Ok(data) = frame.into_data() {
+            event.extend_from_slice(&data);
+            if event[header..].windows(2).any(|pair| pair == b"\n\n") {
+                break;
+            }
+        }
+    }
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_ranges_are_capped() {
+        assert_eq!(
+            limited_range("bytes=0-").as_deref(),
+            Some("bytes=0-8388607")
+        );
+        assert_eq!(
+            limited_range("bytes=100-").as_deref(),
+            Some("bytes=100-8388707")
+        );
+    }
+
+    #[test]
+    fn large_ranges_are_capped() {
+        assert_eq!(
+            limited_range("bytes=0-999999999").as_deref(),
+            Some("bytes=0-8388607")
+        );
+    }
+
+    #[test]
+    fn small_and_unsupported_ranges_are_kept() {
+        assert_eq!(limited_range("bytes=0-1023"), None);
+        assert_eq!(limited_range("bytes=-500"), None);
+        assert_eq!(limited_range("bytes=0-10, 20-30"), None);
+        assert_eq!(limited_range("items=0-"), None);
+    }
+}
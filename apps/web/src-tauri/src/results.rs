@@ -0,0 +1,287 @@
+//! Saving job results straight into a folder chosen by the user.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::history::JobHistory;
+use crate::jobs::{self, JobInfo};
+
+/// Used when no naming template is given.
+pub const DEFAULT_TEMPLATE: &str = "{stem}_{processor}.{ext}";
+
+/// What to do when the destination file already exists.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OnConflict {
+    Overwrite,
+    /// Save as `name (1).ext`, `name (2).ext`, …
+    #[default]
+    Rename,
+    Skip,
+}
+
+/// Whether the result stays available in the sidecar after saving.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SaveMode {
+    #[default]
+    Copy,
+    /// Move the file out of the jobs dir; the job can't be downloaded again.
+    Move,
+}
+
+/// How results are named and written.
+#[derive(Clone, Debug, Default)]
+pub struct SaveOptions {
+    pub template: Option<String>,
+    pub on_conflict: OnConflict,
+    pub mode: SaveMode,
+}
+
+/// Outcome for one job.
+#[derive(Debug, Serialize)]
+pub struct SavedResult {
+    pub job_id: String,
+    /// Where the result was written, `None` if it wasn't.
+    pub path: Option<PathBuf>,
+    /// Why nothing was written (conflict skipped, job failed, …).
+    pub skipped: Option<String>,
+}
+
+/// Save the result of one completed job into `dest_dir`.
+pub async fn save_job(
+    app: &AppHandle,
+    job_id: &str,
+    dest_dir: &Path,
+    options: &SaveOptions,
+) -> Result<SavedResult, String> {
+    let job = jobs::fetch(app, job_id).await?;
+    let source = jobs::result_file(app, &job)?;
+    let saved = save(&job, &source, dest_dir, options, None)?;
+    record(app, &saved);
+    Ok(saved)
+}
+
+/// Save every completed job of a batch into `dest_dir`.
+///
+/// Jobs that failed, aren't finished or couldn't be written are reported
+/// as skipped instead of failing the whole batch. `{index}` in the template
+/// is the 1-based position of the job in the batch.
+pub async fn save_batch(
+    app: &AppHandle,
+    batch_id: &str,
+    dest_dir: &Path,
+    options: &SaveOptions,
+) -> Result<Vec<SavedResult>, String> {
+    if !dest_dir.is_dir() {
+        return Err(format!("{} is not a folder", dest_dir.display()));
+    }
+    let jobs = jobs::fetch_batch(app, batch_id).await?;
+    let mut saved = Vec::with_capacity(jobs.len());
+    for (index, job) in jobs.iter().enumerate() {
+        let result = match jobs::result_file(app, job) {
+            Ok(source) => save(job, &source, dest_dir, options, Some(index + 1)),
+            Err(reason) => Err(job.error.clone().unwrap_or(reason)),
+        }
+        .unwrap_or_else(|reason| SavedResult {
+            job_id: job.id.clone(),
+            path: None,
+            skipped: Some(reason),
+        });
+        record(app, &result);
+        saved.push(result);
+    }
+    Ok(saved)
+}
+
+/// Point the job's history record at the saved copy.
+pub fn record(app: &AppHandle, saved: &SavedResult) {
+    if let Some(path) = &saved.path {
+        let _ = app.state::<JobHistory>().set_output(&saved.job_id, path);
+    }
+}
+
+/// Write the result `source` of `job` into `dest_dir`.
+///
+/// `index` is what `{index}` stands for in the template.
+pub fn save(
+    job: &JobInfo,
+    source: &Path,
+    dest_dir: &Path,
+    options: &SaveOptions,
+    index: Option<usize>,
+) -> Result<SavedResult, String> {
+    if !dest_dir.is_dir() {
+        return Err(format!("{} is not a folder", dest_dir.display()));
+    }
+    let template = options.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+    let name = render(template, job, source, index)?;
+    let mut dest = dest_dir.join(&name);
+
+    if dest.exists() {
+        match options.on_conflict {
+            OnConflict::Overwrite => {}
+            OnConflict::Skip => {
+                return Ok(SavedResult {
+                    job_id: job.id.clone(),
+                    path: None,
+                    skipped: Some(format!("{name} already exists")),
+                })
+            }
+            OnConflict::Rename => dest = free_name(&dest),
+        }
+    }
+
+    let written = match options.mode {
+        SaveMode::Copy => fs::copy(source, &dest).map(|_| ()),
+        SaveMode::Move => move_file(source, &dest),
+    };
+    written.map_err(|e| format!("Failed to save {}: {e}", dest.display()))?;
+    Ok(SavedResult {
+        job_id: job.id.clone(),
+        path: Some(dest),
+        skipped: None,
+    })
+}
+
+/// Fill in a naming template.
+///
+/// Placeholders: `{stem}` and `{name}` of the original file, `{ext}` of the
+/// result (without the dot), `{processor}`, `{job}` and `{index}`.
+fn render(
+    template: &str,
+    job: &JobInfo,
+    source: &Path,
+    index: Option<usize>,
+) -> Result<String, String> {
+    let original = Path::new(&job.original_filename);
+    let stem = original
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| job.original_filename.clone());
+    let ext = source
+        .extension()
+        .map(|ext| ext.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in {template}"))?;
+        let value = match &rest[start + 1..start + end] {
+            "stem" => stem.clone(),
+            "name" => job.original_filename.clone(),
+            "ext" => ext.clone(),
+            "processor" => job.processor_id.clone(),
+            "job" => job.id.clone(),
+            "index" => index.unwrap_or(1).to_string(),
+            other => return Err(format!("Unknown placeholder {{{other}}}")),
+        };
+        name.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+
+    // Results always land directly in the chosen folder.
+    let name = name.trim().trim_end_matches('.').to_string();
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(format!(
+            "Template {template} doesn't give a valid file name"
+        ));
+    }
+    Ok(name)
+}
+
+/// First of `name (1).ext`, `name (2).ext`, … that doesn't exist yet.
+pub fn free_name(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{stem} ({n}){ext}")))
+        .find(|candidate| !candidate.exists())
+        .expect("some numbered name is free")
+}
+
+/// Rename, falling back to copy and delete across file systems.
+pub fn move_file(source: &Path, dest: &Path) -> io::Result<()> {
+    if fs::rename(source, dest).is_ok() {
+        return Ok(());
+    }
+    fs::copy(source, dest)?;
+    fs::remove_file(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job() -> JobInfo {
+        serde_json::from_value(serde_json::json!({
+            "id": "abc",
+            "processor_id": "rembg",
+            "original_filename": "photo.jpg",
+            "status": "completed",
+        }))
+        .unwrap()
+    }
+
+    fn render_png(template: &str) -> Result<String, String> {
+        render(template, &job(), Path::new("/jobs/abc/result.png"), Some(3))
+    }
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        assert_eq!(
+            render_png("{index}-{stem}_{processor}.{ext}").as_deref(),
+            Ok("3-photo_rembg.png")
+        );
+        assert_eq!(render_png("{job} {name}").as_deref(), Ok("abc photo.jpg"));
+    }
+
+    #[test]
+    fn bad_templates_are_rejected() {
+        assert_eq!(
+            render_png("{stem").unwrap_err(),
+            "Unclosed placeholder in {stem"
+        );
+        assert_eq!(
+            render_png("{size}.png").unwrap_err(),
+            "Unknown placeholder {size}"
+        );
+        assert!(render_png("out/{stem}.{ext}").is_err());
+        assert!(render_png("..\\{stem}").is_err());
+        assert!(render_png(" . ").is_err());
+    }
+
+    #[test]
+    fn trailing_dots_are_trimmed() {
+        assert_eq!(render_png("{stem}.").as_deref(), Ok("photo"));
+        assert_eq!(render_png("{stem}.{ext}..").as_deref(), Ok("photo.png"));
+    }
+
+    #[test]
+    fn free_name_counts_up() {
+        let dir = std::env::temp_dir().join(format!("vimix-free-name-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let taken = dir.join("photo.png");
+        fs::write(&taken, b"").unwrap();
+        fs::write(dir.join("photo (1).png"), b"").unwrap();
+
+        assert_eq!(free_name(&taken), dir.join("photo (2).png"));
+        assert_eq!(free_name(&dir.join("notes")), dir.join("notes (1)"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
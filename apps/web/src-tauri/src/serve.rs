@@ -0,0 +1,251 @@
+//! `vimix serve`: the sidecar API as a long-lived local service, without
+//! a window, e.g. as the backend of the MCP server or a systemd user unit.
+//!
+//! The sidecar is supervised like in the app (restarted with backoff after
+//! a crash) and stopped cleanly on SIGTERM or Ctrl+C. Clients find it
+//! through files in the data dir: `vimix.port` with the port it listens
+//! on and `vimix.token` with the API token; `vimix.pid` holds the service's
+//! process id.
+
+use std::fs;
+use std::io::{self, Write};
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use clap::Args;
+
+use crate::cli::{APP_IDENTIFIER, EXIT_BACKEND};
+use crate::headless::{HeadlessOptions, HeadlessSidecar};
+use crate::sidecar::{ApiToken, BASE_BACKOFF, MAX_RESTARTS, STABLE_UPTIME};
+
+/// Port the sidecar has always used outside the app.
+const DEFAULT_PORT: u16 = 8787;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Address to listen on: `127.0.0.1` or `0.0.0.0`. The service is
+    /// health-checked over `127.0.0.1`, so IPv6 addresses aren't accepted.
+    #[arg(long, default_value = "127.0.0.1", value_parser = parse_host)]
+    host: Ipv4Addr,
+    /// Port to listen on; `0` lets the OS pick one (see `vimix.port`).
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+    /// Where jobs and the pid, port and token files are kept. Defaults to
+    /// a `serve` folder in the app's cache dir.
+    #[arg(long)]
+    data_dir: Option<PathBuf>,
+}
+
+/// The files announcing a running service, removed when it stops.
+struct ServiceFiles {
+    pid: PathBuf,
+    port: PathBuf,
+    token: PathBuf,
+}
+
+impl ServiceFiles {
+    fn new(dir: &Path) -> Self {
+        Self {
+            pid: dir.join("vimix.pid"),
+            port: dir.join("vimix.port"),
+            token: dir.join("vimix.token"),
+        }
+    }
+
+    fn remove(&self) {
+        for path in [&self.pid, &self.port, &self.token] {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// The service itself always connects over loopback, like the app.
+fn parse_host(arg: &str) -> Result<Ipv4Addr, String> {
+    let ip: Ipv4Addr = arg
+        .parse()
+        .map_err(|_| format!("invalid IPv4 address {arg}"))?;
+    if !ip.is_loopback() && !ip.is_unspecified() {
+        return Err(format!("{arg} is not 127.0.0.1 or 0.0.0.0"));
+    }
+    Ok(ip)
+}
+
+/// Run the service until it is told to stop. Returns the exit code.
+pub async fn run(args: ServeArgs) -> Result<i32, String> {
+    let data_dir = args
+        .data_dir
+        .or_else(default_data_dir)
+        .ok_or("Could not determine a data dir, pass --data-dir")?;
+    fs::create_dir_all(&data_dir)
+        .map_err(|e| format!("Failed to create {}: {e}", data_dir.display()))?;
+
+    let files = ServiceFiles::new(&data_dir);
+    if let Some(pid) = running_pid(&files.pid) {
+        return Err(format!("Already running (pid {pid})"));
+    }
+    // Registered before anything slow, so an early SIGTERM isn't lost.
+    let mut stop = StopSignals::register().map_err(|e| e.to_string())?;
+
+    // Keep a token from the environment so clients can be configured up front.
+    let token = std::env::var("VIMIX_API_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .unwrap_or_else(|| ApiToken::generate().0);
+    write_private(&files.pid, &std::process::id().to_string())?;
+    write_private(&files.token, &token)?;
+
+    let options = HeadlessOptions {
+        host: args.host.to_string(),
+        port: args.port,
+        data_dir,
+        token,
+        verbose: true,
+    };
+    let code = supervise(&options, &files, &mut stop).await;
+    files.remove();
+    Ok(code)
+}
+
+/// Keep the sidecar running until a stop signal, restarting it after
+/// crashes. Mirrors the app's supervisor: a sidecar that never comes up,
+/// or crashes `MAX_RESTARTS` times in a row, ends the service.
+async fn supervise(options: &HeadlessOptions, files: &ServiceFiles, stop: &mut StopSignals) -> i32 {
+    let mut attempt: u32 = 0;
+    let mut ever_ready = false;
+
+    loop {
+        let started = Instant::now();
+        let exit = match HeadlessSidecar::start(options).await {
+            Ok(mut sidecar) => {
+                ever_ready = true;
+                if let Some(port) = sidecar.port() {
+                    let _ = write_private(&files.port, &port.to_string());
+                    eprintln!("Vimix API listening on {}:{port}", options.host);
+                }
+                let exit = tokio::select! {
+                    exit = sidecar.exited() => Some(exit),
+                    _ = stop.recv() => None,
+                };
+                match exit {
+                    Some(exit) => exit,
+                    None => {
+                        eprintln!("Stopping");
+                        sidecar.shutdown().await;
+                        return 0;
+                    }
+                }
+            }
+            Err(reason) if !ever_ready => {
+                eprintln!("error: {reason}");
+                return EXIT_BACKEND;
+            }
+            Err(reason) => reason,
+        };
+        let _ = fs::remove_file(&files.port);
+
+        if started.elapsed() >= STABLE_UPTIME {
+            attempt = 0;
+        }
+        if attempt >= MAX_RESTARTS {
+            eprintln!("error: Sidecar crashed {MAX_RESTARTS} times in a row ({exit})");
+            return EXIT_BACKEND;
+        }
+        eprintln!("{exit}; restarting");
+        tokio::select! {
+            _ = tokio::time::sleep(BASE_BACKOFF * 2u32.pow(attempt)) => {}
+            _ = stop.recv() => return 0,
+        }
+        attempt += 1;
+    }
+}
+
+/// `serve` folder next to where the app keeps its own sidecar data, so
+/// the app and the service can run side by side.
+fn default_data_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(APP_IDENTIFIER).join("serve"))
+}
+
+/// The pid in `path` if that process is still alive.
+fn running_pid(path: &Path) -> Option<u32> {
+    let pid: u32 = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    is_alive(pid).then_some(pid)
+}
+
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks that the process exists.
+    unsafe { libc::kill(pid as i32, 0) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_alive(_pid: u32) -> bool {
+    // A stale file is the common case; a second service would fail to
+    // bind the port anyway.
+    false
+}
+
+/// Write a file only the current user can read; it may hold the token.
+fn write_private(path: &Path, contents: &str) -> Result<(), String> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{contents}"))
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// SIGTERM and SIGINT on Unix; Ctrl+C, closing the console and system
+/// shutdown on Windows.
+struct StopSignals {
+    #[cfg(unix)]
+    term: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    int: tokio::signal::unix::Signal,
+    #[cfg(windows)]
+    ctrl_c: tokio::signal::windows::CtrlC,
+    #[cfg(windows)]
+    close: tokio::signal::windows::CtrlClose,
+    #[cfg(windows)]
+    shutdown: tokio::signal::windows::CtrlShutdown,
+}
+
+impl StopSignals {
+    #[cfg(unix)]
+    fn register() -> io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Self {
+            term: signal(SignalKind::terminate())?,
+            int: signal(SignalKind::interrupt())?,
+        })
+    }
+
+    #[cfg(windows)]
+    fn register() -> io::Result<Self> {
+        use tokio::signal::windows;
+        Ok(Self {
+            ctrl_c: windows::ctrl_c()?,
+            close: windows::ctrl_close()?,
+            shutdown: windows::ctrl_shutdown()?,
+        })
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = self.term.recv() => {}
+            _ = self.int.recv() => {}
+        }
+        #[cfg(windows)]
+        tokio::select! {
+            _ = self.ctrl_c.recv() => {}
+            _ = self.close.recv() => {}
+            _ = self.shutdown.recv() => {}
+        }
+    }
+}
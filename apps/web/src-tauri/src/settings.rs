@@ -0,0 +1,235 @@
+//! User settings, persisted as `settings.json` in the app config dir.
+
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::Transport;
+use crate::json_file;
+use crate::toolchain::Tool;
+
+/// Version written to `settings.json`. Bump it and add a step to
+/// `migrate` whenever the format changes incompatibly.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Sidecar arguments the app sets itself and users can't override.
+const RESERVED_ARGS: [&str; 4] = ["--host", "--port", "--uds", "--supervised"];
+
+/// Everything users can configure. Missing fields take their defaults.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub tools: ToolPaths,
+    pub sidecar: SidecarSettings,
+}
+
+/// Custom builds of the external tools, used instead of the bundled ones.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolPaths {
+    pub ffmpeg: Option<PathBuf>,
+    pub ffprobe: Option<PathBuf>,
+    pub img2webp: Option<PathBuf>,
+}
+
+impl ToolPaths {
+    pub fn get(&self, tool: Tool) -> Option<&Path> {
+        match tool {
+            Tool::Ffmpeg => self.ffmpeg.as_deref(),
+            Tool::Ffprobe => self.ffprobe.as_deref(),
+            Tool::Img2webp => self.img2webp.as_deref(),
+        }
+    }
+}
+
+/// How the sidecar is launched.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SidecarSettings {
+    /// Address the sidecar binds to (default `127.0.0.1`). The app itself
+    /// always connects to `127.0.0.1`, so this must be an IPv4 loopback
+    /// address or `0.0.0.0`.
+    pub host: Option<String>,
+    /// Fixed port instead of one picked by the OS.
+    pub port: Option<u16>,
+    /// Takes effect on the next launch of the app, since the webview has to
+    /// be reloaded to switch. `unix` is ignored on Windows.
+    pub transport: Transport,
+    /// Appended to the sidecar command line, e.g. `["--log-level", "debug"]`.
+    pub extra_args: Vec<String>,
+    /// rembg models to use instead of the bundled ones (`U2NET_HOME`).
+    pub model_dir: Option<PathBuf>,
+}
+
+impl Settings {
+    /// Check the settings before they are saved.
+    pub fn validate(&self) -> Result<(), String> {
+        for tool in Tool::ALL {
+            if let Some(path) = self.tools.get(tool) {
+                if !path.is_absolute() {
+                    return Err(format!("{} path must be absolute", tool.name()));
+                }
+            }
+        }
+        if let Some(dir) = &self.sidecar.model_dir {
+            if !dir.is_absolute() {
+                return Err("Model directory must be absolute".to_string());
+            }
+        }
+        if let Some(host) = &self.sidecar.host {
+            let ip: Ipv4Addr = host
+                .parse()
+                .map_err(|_| format!("Sidecar host must be an IPv4 address, got {host}"))?;
+            if !ip.is_loopback() && !ip.is_unspecified() {
+                return Err(format!(
+                    "Sidecar host must be 127.0.0.1 or 0.0.0.0, got {host}"
+                ));
+            }
+        }
+        if self.sidecar.port == Some(0) {
+            return Err("Sidecar port must be between 1 and 65535".to_string());
+        }
+        if let Some(arg) = self
+            .sidecar
+            .extra_args
+            .iter()
+            .find(|arg| RESERVED_ARGS.contains(&arg.split('=').next().unwrap_or_default()))
+        {
+            return Err(format!("{arg} is set by the app and can't be overridden"));
+        }
+        Ok(())
+    }
+}
+
+/// On-disk layout: the settings plus the schema they were written with.
+#[derive(Serialize)]
+struct SettingsFile<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    settings: &'a Settings,
+}
+
+/// The schema a parsed `settings.json` was written with; `0` from before
+/// it was recorded.
+fn schema_version(value: &serde_json::Value) -> u64 {
+    value
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0)
+}
+
+/// Upgrade a parsed `settings.json` from an older schema to the current one.
+fn migrate(mut value: serde_json::Value) -> Result<Settings, String> {
+    if let Some(object) = value.as_object_mut() {
+        object.remove("schema_version");
+    }
+    serde_json::from_value(value).map_err(|e| format!("Invalid settings.json: {e}"))
+}
+
+/// Settings loaded from disk, managed as Tauri state.
+pub struct SettingsStore {
+    path: PathBuf,
+    current: Mutex<Settings>,
+    /// Set when `settings.json` was written by a newer Vimix, which this
+    /// one must not overwrite.
+    newer: Option<String>,
+}
+
+impl SettingsStore {
+    /// Load `settings.json` from `dir`.
+    ///
+    /// A missing file gives the defaults. An unreadable one is moved aside
+    /// to `settings.json.bak` so the next save doesn't silently replace it.
+    /// One from a newer Vimix is left in place and the defaults are used
+    /// until it is back.
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join("settings.json");
+        let (settings, newer) = json_file::load(&path, |text| {
+            let value: serde_json::Value =
+                serde_json::from_str(text).map_err(|e| format!("Invalid settings.json: {e}"))?;
+            let version = schema_version(&value);
+            if version > u64::from(SCHEMA_VERSION) {
+                let reason =
+                    format!("settings.json was written by a newer Vimix (schema {version})");
+                return Ok((Settings::default(), Some(reason)));
+            }
+            Ok((migrate(value)?, None))
+        })
+        .unwrap_or_default();
+        Self {
+            path,
+            current: Mutex::new(settings),
+            newer,
+        }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Validate and persist `settings`. Returns whether anything changed.
+    pub fn update(&self, settings: Settings) -> Result<bool, String> {
+        settings.validate()?;
+        let mut current = self.current.lock().unwrap();
+        if *current == settings {
+            return Ok(false);
+        }
+        if let Some(reason) = &self.newer {
+            return Err(format!("{reason}, so it can't be changed here"));
+        }
+        self.save(&settings)?;
+        *current = settings;
+        Ok(true)
+    }
+
+    fn save(&self, settings: &Settings) -> Result<(), String> {
+        json_file::write(
+            &self.path,
+            &SettingsFile {
+                schema_version: SCHEMA_VERSION,
+                settings,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn settings_from_a_newer_vimix_are_left_alone() {
+        let dir = std::env::temp_dir().join(format!("vimix-settings-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+        let json = r#"{"schema_version": 99, "tools": {"ffmpeg": 1}}"#;
+        fs::write(&path, json).unwrap();
+
+        let store = SettingsStore::load(&dir);
+        assert_eq!(store.get(), Settings::default());
+        let mut settings = Settings::default();
+        settings.sidecar.extra_args.push("--verbose".to_string());
+        assert!(store.update(settings).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), json);
+        assert!(!path.with_extension("json.bak").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unreadable_settings_are_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("vimix-settings-bad-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        let store = SettingsStore::load(&dir);
+        assert_eq!(store.get(), Settings::default());
+        assert!(!path.exists());
+        assert!(path.with_extension("json.bak").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,659 @@
+//! Python sidecar lifecycle: spawning, supervision and automatic restarts.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{Method, Request};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::Receiver;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+
+use crate::client::{self, Endpoint, Transport};
+use crate::config::SidecarConfig;
+use crate::logs::{self, LogStream};
+use crate::settings::{Settings, SettingsStore};
+use crate::toolchain::{self, ToolInfo};
+
+/// Give up after this many consecutive crashes.
+pub const MAX_RESTARTS: u32 = 5;
+
+/// Delay before the first restart; doubled after every consecutive crash.
+pub const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// A sidecar that stayed up this long is considered healthy again,
+/// so its next crash starts the backoff from scratch.
+pub const STABLE_UPTIME: Duration = Duration::from_secs(30);
+
+/// How long a freshly spawned sidecar may take to answer `/health`.
+/// The PyInstaller bundle unpacks itself on first launch, which is slow.
+pub const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Delay between two `/health` probes, also used as the per-request timeout.
+pub const PROBE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long the sidecar gets to drain and exit on its own when the app quits.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stdout lines starting with this are control messages, not log output.
+pub const CONTROL_PREFIX: &str = "@vimix ";
+
+/// Header carrying the per-launch API token on every sidecar request.
+pub const TOKEN_HEADER: &str = "x-vimix-token";
+
+/// Process creation flag that keeps the console programs the app spawns
+/// (sidecar, tools, `taskkill`) from flashing a console window.
+#[cfg(windows)]
+pub const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Holds the API port so the frontend can query it via IPC.
+///
+/// The sidecar binds the port itself and reports it back over stdout, so
+/// this is `None` until the first launch is listening, and stays `None`
+/// with the Unix socket transport. Restarts try to keep the same port and
+/// only move when it has been taken meanwhile.
+#[derive(Default)]
+pub struct ApiPort(pub Mutex<Option<u16>>);
+
+/// Random secret generated once per launch and required by the sidecar
+/// on every request, so other local processes and browser tabs can't
+/// drive the API even though it listens on a localhost port.
+pub struct ApiToken(pub String);
+
+impl ApiToken {
+    pub fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().simple().to_string())
+    }
+}
+
+/// The running sidecar process and what's needed to stop it.
+#[derive(Default)]
+pub struct Sidecar {
+    child: Mutex<Option<CommandChild>>,
+    /// Where the current launch reported it is listening.
+    listening: Mutex<Option<Endpoint>>,
+    /// Process group led by the sidecar (Unix only), reported at startup.
+    /// Kept after the sidecar exits so orphaned grandchildren can be reaped.
+    pgid: Mutex<Option<i32>>,
+    /// Set once the app is quitting, so exits are no longer restarted.
+    stopping: AtomicBool,
+    /// Set by `reload` and cleared by the supervisor once it has seen the
+    /// old sidecar exit, so the exit is followed by an immediate relaunch
+    /// instead of a backoff.
+    reloading: AtomicBool,
+    /// Set while `reload` is still reaping the old process group; the
+    /// relaunch waits for it so the new sidecar isn't killed along with it.
+    draining: AtomicBool,
+}
+
+impl Sidecar {
+    fn is_running(&self) -> bool {
+        self.child.lock().unwrap().is_some()
+    }
+}
+
+/// Where the sidecar API can currently be reached, if it is listening.
+pub fn endpoint(app: &AppHandle) -> Option<Endpoint> {
+    app.state::<Sidecar>().listening.lock().unwrap().clone()
+}
+
+/// Call the sidecar API on the app's behalf and decode the JSON response.
+///
+/// When the sidecar rejects the request, the error is FastAPI's `detail`.
+pub async fn call<T: DeserializeOwned>(
+    app: &AppHandle,
+    method: Method,
+    path: &str,
+    body: Option<&serde_json::Value>,
+) -> Result<T, String> {
+    let endpoint = endpoint(app).ok_or("Backend is not ready")?;
+    client::call(&endpoint, &app.state::<ApiToken>().0, method, path, body).await
+}
+
+/// Wait until the sidecar is ready to take `call`s.
+///
+/// Fails with the reason if it gives up starting instead.
+pub async fn wait_ready(app: &AppHandle) -> Result<(), String> {
+    loop {
+        let status = app.state::<Backend>().0.lock().unwrap().clone();
+        match status {
+            BackendStatus::Ready { .. } => return Ok(()),
+            BackendStatus::Failed { reason } => return Err(reason),
+            BackendStatus::Starting => {}
+        }
+        tokio::time::sleep(PROBE_INTERVAL).await;
+    }
+}
+
+/// The transport to use for this launch of the app, from the settings.
+///
+/// TCP stays the default, and the only option on Windows.
+pub fn transport(settings: &Settings) -> Transport {
+    match settings.sidecar.transport {
+        Transport::Unix if cfg!(unix) => Transport::Unix,
+        _ => Transport::Tcp,
+    }
+}
+
+/// Control message printed by the sidecar on stdout as `@vimix {json}`.
+#[derive(Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Control {
+    ProcessGroup {
+        pgid: i32,
+    },
+    Listening {
+        #[serde(default)]
+        port: Option<u16>,
+        #[serde(default)]
+        socket: Option<PathBuf>,
+    },
+}
+
+/// Lifecycle state of the sidecar, as reported by `get_backend_status`.
+#[derive(Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum BackendStatus {
+    Starting,
+    Ready { port: Option<u16> },
+    Failed { reason: String },
+}
+
+/// Latest known sidecar status, so late listeners don't miss `backend-ready`.
+pub struct Backend(pub Mutex<BackendStatus>);
+
+impl Default for Backend {
+    fn default() -> Self {
+        Self(Mutex::new(BackendStatus::Starting))
+    }
+}
+
+/// Payload of the `backend-ready` event.
+#[derive(Clone, Serialize)]
+struct BackendReady {
+    port: Option<u16>,
+}
+
+/// Payload of the `backend-restarted` event.
+#[derive(Clone, Serialize)]
+struct BackendRestarted {
+    port: Option<u16>,
+    attempt: u32,
+}
+
+/// Payload of the `backend-failed` event.
+#[derive(Clone, Serialize)]
+struct BackendFailed {
+    reason: String,
+}
+
+/// Describe a sidecar launch, preferably on `port`.
+///
+/// Without a preferred port the sidecar binds port 0 and reports the
+/// port it got, which closes the race of picking a free port up front.
+/// A port and host from the settings take precedence. With the Unix
+/// transport it listens on a socket in the runtime dir instead and the
+/// port and host are ignored.
+///
+/// The ffmpeg/ffprobe/img2webp paths resolved by the toolchain preflight,
+/// and the model directory from the settings, reach the Python side
+/// through the sidecar's own environment; the app's environment is left
+/// untouched.
+fn sidecar_config(
+    app: &AppHandle,
+    port: Option<u16>,
+    tools: &[ToolInfo],
+) -> Result<SidecarConfig, String> {
+    let settings = app.state::<SettingsStore>().get().sidecar;
+    let mut config = match *app.state::<Transport>() {
+        Transport::Tcp => SidecarConfig::new(tools)
+            .arg("--host")
+            .arg(settings.host.as_deref().unwrap_or("127.0.0.1"))
+            .arg("--port")
+            .arg(settings.port.or(port).unwrap_or(0).to_string()),
+        Transport::Unix => SidecarConfig::new(tools)
+            .arg("--uds")
+            .arg(socket_path(app)?.to_string_lossy()),
+    };
+    let data_dir = data_dir(app)?;
+    std::fs::create_dir_all(&data_dir)
+        .map_err(|e| format!("Failed to create {}: {e}", data_dir.display()))?;
+
+    config.working_dir = Some(data_dir.clone());
+    config.data_dir = Some(data_dir);
+    config.model_dir = settings.model_dir;
+
+    config.args.extend(settings.extra_args);
+    Ok(config
+        .arg("--supervised")
+        .env("PYTHONUNBUFFERED", "1")
+        .env("VIMIX_API_TOKEN", &app.state::<ApiToken>().0))
+}
+
+/// Where the sidecar keeps uploads and job outputs: the app cache dir.
+pub fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to resolve cache directory: {e}"))
+}
+
+/// The sidecar's job output directory; results are only ever read from here.
+pub fn jobs_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(data_dir(app)?.join("jobs"))
+}
+
+/// Spawn the sidecar binary as described by `config`.
+fn spawn_sidecar(
+    app: &AppHandle,
+    config: &SidecarConfig,
+) -> Result<(Receiver<CommandEvent>, CommandChild), String> {
+    let mut sidecar = app
+        .shell()
+        .sidecar("Vimix-processor")
+        .map_err(|e| format!("Failed to locate sidecar binary: {e}"))?
+        .args(&config.args)
+        .envs(config.envs());
+    if let Some(dir) = &config.working_dir {
+        sidecar = sidecar.current_dir(dir);
+    }
+
+    sidecar
+        .spawn()
+        .map_err(|e| format!("Failed to spawn sidecar: {e}"))
+}
+
+/// Socket path for the Unix transport: the runtime dir where there is one
+/// (Linux), the app cache dir otherwise (macOS).
+fn socket_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .runtime_dir()
+        .map(|dir| dir.join("vimix"))
+        .or_else(|_| app.path().app_cache_dir())
+        .map_err(|e| format!("Failed to resolve socket directory: {e}"))?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    Ok(dir.join("sidecar.sock"))
+}
+
+/// Start the sidecar and keep it running for the app's lifetime.
+///
+/// The supervisor owns the sidecar's event stream. Each launch is probed
+/// on `/health` and announced with `backend-ready`. When the process
+/// terminates it is restarted with exponential backoff, and the frontend
+/// is notified through `backend-restarted` once it is healthy again.
+/// A first launch that never comes up, or a sidecar that crashes (or
+/// hangs on restart) `MAX_RESTARTS` times in a row, is reported through
+/// `backend-failed`; `restart` can try again.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move { supervise(app).await });
+}
+
+async fn supervise(app: AppHandle) {
+    let mut attempt: u32 = 0;
+    let mut ever_ready = false;
+    let mut preferred_port: Option<u16> = None;
+
+    loop {
+        if app.state::<Sidecar>().stopping.load(Ordering::SeqCst) {
+            return;
+        }
+        set_status(&app, BackendStatus::Starting);
+
+        let tools = toolchain::preflight(&app).await;
+        let spawned = sidecar_config(&app, preferred_port, &tools)
+            .and_then(|config| spawn_sidecar(&app, &config));
+        let (mut rx, child) = match spawned {
+            Ok(spawned) => spawned,
+            Err(reason) => return fail(&app, reason),
+        };
+        let sidecar = app.state::<Sidecar>();
+        *sidecar.child.lock().unwrap() = Some(child);
+        *sidecar.listening.lock().unwrap() = None;
+        *sidecar.pgid.lock().unwrap() = None;
+
+        let started = Instant::now();
+        let early_exit = tokio::select! {
+            exit = wait_for_exit(&app, &mut rx) => Some(exit),
+            probe = wait_until_healthy(&app) => match probe {
+                Ok(endpoint) => {
+                    let port = endpoint.port();
+                    ever_ready = true;
+                    preferred_port = port;
+                    *app.state::<ApiPort>().0.lock().unwrap() = port;
+                    set_status(&app, BackendStatus::Ready { port });
+                    let _ = app.emit("backend-ready", BackendReady { port });
+                    if attempt > 0 {
+                        let _ = app.emit("backend-restarted", BackendRestarted { port, attempt });
+                    }
+                    None
+                }
+                // A first launch that never comes up is broken, not flaky: don't retry.
+                Err(reason) if !ever_ready => {
+                    kill_all(&app);
+                    return fail(&app, reason);
+                }
+                // A relaunch after a crash that hangs counts as another crash.
+                Err(reason) => {
+                    kill_all(&app);
+                    Some(Exit::unhealthy(reason))
+                }
+            },
+        };
+        let became_ready = early_exit.is_none();
+        let exit = match early_exit {
+            Some(exit) => exit,
+            None => wait_for_exit(&app, &mut rx).await,
+        };
+        app.state::<Sidecar>().child.lock().unwrap().take();
+
+        if app.state::<Sidecar>().stopping.load(Ordering::SeqCst) {
+            return;
+        }
+        // Stopped to apply new settings: once `reload` has reaped the old
+        // process group, relaunch right away and judge the new config on its own.
+        if app.state::<Sidecar>().reloading.load(Ordering::SeqCst) {
+            while app.state::<Sidecar>().draining.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            app.state::<Sidecar>()
+                .reloading
+                .store(false, Ordering::SeqCst);
+            attempt = 0;
+            ever_ready = false;
+            preferred_port = None;
+            continue;
+        }
+        // A sidecar that never came up is broken, not flaky: don't retry.
+        if !ever_ready {
+            return fail(&app, exit.describe());
+        }
+        if became_ready && started.elapsed() >= STABLE_UPTIME {
+            attempt = 0;
+        }
+        // The old port may have been taken meanwhile: let the next launch pick any.
+        if !became_ready {
+            preferred_port = None;
+        }
+        if attempt >= MAX_RESTARTS {
+            return fail(
+                &app,
+                format!(
+                    "Sidecar crashed {MAX_RESTARTS} times in a row ({})",
+                    exit.describe()
+                ),
+            );
+        }
+
+        set_status(&app, BackendStatus::Starting);
+        tokio::time::sleep(BASE_BACKOFF * 2u32.pow(attempt)).await;
+        attempt += 1;
+    }
+}
+
+fn set_status(app: &AppHandle, status: BackendStatus) {
+    *app.state::<Backend>().0.lock().unwrap() = status;
+}
+
+fn fail(app: &AppHandle, reason: String) {
+    set_status(
+        app,
+        BackendStatus::Failed {
+            reason: reason.clone(),
+        },
+    );
+    let _ = app.emit("backend-failed", BackendFailed { reason });
+}
+
+fn handle_control(app: &AppHandle, json: &str) {
+    let sidecar = app.state::<Sidecar>();
+    match serde_json::from_str::<Control>(json) {
+        Ok(Control::ProcessGroup { pgid }) => *sidecar.pgid.lock().unwrap() = Some(pgid),
+        Ok(Control::Listening { port, socket }) => {
+            let endpoint = match (port, socket) {
+                (Some(port), _) => Endpoint::Tcp(port),
+                #[cfg(unix)]
+                (None, Some(path)) => Endpoint::Unix(path),
+                _ => return,
+            };
+            *sidecar.listening.lock().unwrap() = Some(endpoint);
+        }
+        // Unknown messages come from a newer sidecar; ignore them.
+        Err(_) => {}
+    }
+}
+
+/// Start the supervisor again after it gave up.
+///
+/// Returns `false` if the sidecar is not in the failed state (already
+/// starting or running), so repeated clicks can't spawn two sidecars.
+pub fn restart(app: &AppHandle) -> bool {
+    {
+        let backend = app.state::<Backend>();
+        let mut status = backend.0.lock().unwrap();
+        if !matches!(*status, BackendStatus::Failed { .. }) {
+            return false;
+        }
+        *status = BackendStatus::Starting;
+    }
+    start(app);
+    true
+}
+
+/// Relaunch the sidecar so it picks up changed settings.
+///
+/// A running sidecar is drained like on quit, then the supervisor starts
+/// it again with a fresh config; a failed one is simply started again.
+pub async fn reload(app: &AppHandle) {
+    if restart(app) {
+        return;
+    }
+    let sidecar = app.state::<Sidecar>();
+    if sidecar.stopping.load(Ordering::SeqCst) {
+        return;
+    }
+    // Without a running child the supervisor is about to spawn one and
+    // will read the new settings anyway.
+    if sidecar.is_running() {
+        sidecar.draining.store(true, Ordering::SeqCst);
+        sidecar.reloading.store(true, Ordering::SeqCst);
+        shutdown(app).await;
+        sidecar.draining.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Mark the sidecar as stopping. Returns `false` if shutdown already began.
+pub fn begin_shutdown(app: &AppHandle) -> bool {
+    !app.state::<Sidecar>().stopping.swap(true, Ordering::SeqCst)
+}
+
+/// Stop the sidecar and everything it spawned.
+///
+/// Asks the sidecar to drain via `POST /shutdown` (cancel jobs, delete job
+/// files, stop the MCP server) and gives it `SHUTDOWN_TIMEOUT` to exit.
+/// Whatever is left of its process group is then terminated, so neither
+/// the MCP server on port 8788 nor ffmpeg children outlive the app.
+pub async fn shutdown(app: &AppHandle) {
+    let sidecar = app.state::<Sidecar>();
+    if let Some(endpoint) = endpoint(app) {
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("/shutdown")
+            .header(TOKEN_HEADER, &app.state::<ApiToken>().0)
+            .body(Full::new(Bytes::new()))
+            .expect("valid shutdown request");
+        let _ = tokio::time::timeout(Duration::from_secs(1), client::send(&endpoint, req)).await;
+    }
+
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+    while sidecar.is_running() && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Give stragglers a chance to clean up before the hard kill.
+    #[cfg(unix)]
+    {
+        let pgid = *sidecar.pgid.lock().unwrap();
+        if let Some(pgid) = pgid {
+            // SAFETY: killpg has no memory-safety preconditions.
+            unsafe { libc::killpg(pgid, libc::SIGTERM) };
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+    kill_all(app);
+}
+
+/// Immediately kill the sidecar and its process group. Safe to call repeatedly.
+pub fn kill_all(app: &AppHandle) {
+    let sidecar = app.state::<Sidecar>();
+    let child = sidecar.child.lock().unwrap().take();
+    let pgid = sidecar.pgid.lock().unwrap().take();
+    kill_tree(child.as_ref().map(CommandChild::pid), pgid);
+
+    if let Some(child) = child {
+        let _ = child.kill();
+    }
+}
+
+/// Kill whatever a sidecar left behind: the process group it leads on
+/// Unix, the tree of processes below `pid` on Windows.
+pub fn kill_tree(pid: Option<u32>, pgid: Option<i32>) {
+    #[cfg(unix)]
+    {
+        let _ = pid;
+        if let Some(pgid) = pgid {
+            // SAFETY: killpg has no memory-safety preconditions.
+            unsafe { libc::killpg(pgid, libc::SIGKILL) };
+        }
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+
+        let _ = pgid;
+        // Windows has no process groups to signal; kill the whole tree instead.
+        if let Some(pid) = pid {
+            let _ = std::process::Command::new("taskkill")
+                .args(["/T", "/F", "/PID", &pid.to_string()])
+                .creation_flags(CREATE_NO_WINDOW)
+                .status();
+        }
+    }
+}
+
+/// Wait for the sidecar to report where it listens, then poll `/health` there.
+///
+/// Returns the endpoint once the sidecar answers, or an error after `STARTUP_TIMEOUT`.
+async fn wait_until_healthy(app: &AppHandle) -> Result<Endpoint, String> {
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+
+    loop {
+        let listening = endpoint(app);
+        if let Some(endpoint) = &listening {
+            let req = Request::get("/health")
+                .body(Full::new(Bytes::new()))
+                .expect("valid health request");
+            let probe = tokio::time::timeout(PROBE_INTERVAL, client::send(endpoint, req)).await;
+            if let Ok(Ok(res)) = probe {
+                if res.status().is_success() {
+                    return Ok(endpoint.clone());
+                }
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err(match listening {
+                Some(endpoint) => format!(
+                    "Sidecar did not respond on {endpoint} within {}s",
+                    STARTUP_TIMEOUT.as_secs()
+                ),
+                None => format!(
+                    "Sidecar did not report where it listens within {}s",
+                    STARTUP_TIMEOUT.as_secs()
+                ),
+            });
+        }
+        tokio::time::sleep(PROBE_INTERVAL).await;
+    }
+}
+
+/// How a sidecar process ended.
+struct Exit {
+    code: Option<i32>,
+    signal: Option<i32>,
+    /// Last line the sidecar wrote to stderr; after a crash, the Python exception.
+    last_error: Option<String>,
+    /// Set when the sidecar was killed for not answering `/health`.
+    unhealthy: Option<String>,
+}
+
+impl Exit {
+    fn unhealthy(reason: String) -> Self {
+        Self {
+            code: None,
+            signal: None,
+            last_error: None,
+            unhealthy: Some(reason),
+        }
+    }
+
+    fn describe(&self) -> String {
+        if let Some(reason) = &self.unhealthy {
+            return reason.clone();
+        }
+        let mut msg = match (self.code, self.signal) {
+            (Some(code), _) => format!("Sidecar exited with code {code}"),
+            (None, Some(signal)) => format!("Sidecar was killed by signal {signal}"),
+            (None, None) => "Sidecar exited".to_string(),
+        };
+        if let Some(line) = &self.last_error {
+            msg = format!("{msg}: {line}");
+        }
+        msg
+    }
+}
+
+/// Drain the sidecar's events into the log until it terminates,
+/// handling control messages along the way.
+async fn wait_for_exit(app: &AppHandle, rx: &mut Receiver<CommandEvent>) -> Exit {
+    let mut last_error = None;
+    while let Some(event) = rx.recv().await {
+        match event {
+            CommandEvent::Stdout(bytes) => {
+                let text = String::from_utf8_lossy(&bytes);
+                match text.trim().strip_prefix(CONTROL_PREFIX) {
+                    Some(json) => handle_control(app, json),
+                    None => logs::capture(app, LogStream::Stdout, &bytes),
+                }
+            }
+            CommandEvent::Stderr(bytes) => {
+                logs::capture(app, LogStream::Stderr, &bytes);
+                let line = String::from_utf8_lossy(&bytes).trim().to_string();
+                if !line.is_empty() {
+                    last_error = Some(line);
+                }
+            }
+            CommandEvent::Terminated(payload) => {
+                return Exit {
+                    code: payload.code,
+                    signal: payload.signal,
+                    last_error,
+                    unhealthy: None,
+                };
+            }
+            _ => {}
+        }
+    }
+    Exit {
+        code: None,
+        signal: None,
+        last_error,
+        unhealthy: None,
+    }
+}
@@ -0,0 +1,303 @@
+//! Preflight checks for the external tools the sidecar shells out to.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::settings::{SettingsStore, ToolPaths};
+
+/// How long `<tool> -version` may take before the binary is considered broken.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// External binary used by the processors.
+#[derive(Clone, Copy, Debug)]
+pub enum Tool {
+    Ffmpeg,
+    Ffprobe,
+    Img2webp,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 3] = [Tool::Ffmpeg, Tool::Ffprobe, Tool::Img2webp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ffmpeg => "ffmpeg",
+            Self::Ffprobe => "ffprobe",
+            Self::Img2webp => "img2webp",
+        }
+    }
+
+    /// Environment variable the Python `binary_paths` module reads.
+    pub fn env_var(self) -> &'static str {
+        match self {
+            Self::Ffmpeg => "FFMPEG_BIN",
+            Self::Ffprobe => "FFPROBE_BIN",
+            Self::Img2webp => "IMG2WEBP_BIN",
+        }
+    }
+
+    fn file_name(self) -> String {
+        let ext = if cfg!(windows) { ".exe" } else { "" };
+        format!("{}{ext}", self.name())
+    }
+}
+
+/// Where a tool was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolSource {
+    /// Configured by the user in the settings.
+    Custom,
+    /// Shipped in the app's resources.
+    Bundled,
+    /// Found on the user's `PATH` because no configured or bundled copy works.
+    Path,
+}
+
+/// Result of checking one tool, as returned by `get_toolchain_info`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ToolInfo {
+    pub name: &'static str,
+    #[serde(skip)]
+    pub tool: Tool,
+    pub available: bool,
+    pub source: Option<ToolSource>,
+    pub path: Option<PathBuf>,
+    pub version: Option<String>,
+    /// Why the configured or bundled copy was rejected, if it was.
+    pub problem: Option<String>,
+}
+
+/// Outcome of the last preflight, managed as Tauri state.
+#[derive(Default)]
+pub struct Toolchain(pub Mutex<Vec<ToolInfo>>);
+
+/// Payload of the `toolchain-unavailable` event.
+#[derive(Clone, Serialize)]
+struct ToolchainUnavailable {
+    tools: Vec<&'static str>,
+}
+
+/// Check every tool, store the result and warn the frontend about
+/// missing ones through `toolchain-unavailable`.
+///
+/// Runs on a blocking thread since each check executes the binary.
+pub async fn preflight(app: &AppHandle) -> Vec<ToolInfo> {
+    let bundled_dir = app
+        .path()
+        .resource_dir()
+        .map(|dir| dir.join("resources"))
+        .ok();
+    let overrides = app.state::<SettingsStore>().get().tools;
+    let tools =
+        tauri::async_runtime::spawn_blocking(move || detect(bundled_dir.as_deref(), &overrides))
+            .await
+            .unwrap_or_default();
+
+    *app.state::<Toolchain>().0.lock().unwrap() = tools.clone();
+    let unavailable: Vec<_> = tools
+        .iter()
+        .filter(|info| !info.available)
+        .map(|info| info.name)
+        .collect();
+    if !unavailable.is_empty() {
+        let _ = app.emit(
+            "toolchain-unavailable",
+            ToolchainUnavailable { tools: unavailable },
+        );
+    }
+    tools
+}
+
+/// Resolve every tool: the path configured in the settings, then the
+/// bundled copy in `bundled_dir`, then the first working one on `PATH`.
+pub fn detect(bundled_dir: Option<&Path>, overrides: &ToolPaths) -> Vec<ToolInfo> {
+    Tool::ALL
+        .into_iter()
+        .map(|tool| resolve(tool, bundled_dir, overrides.get(tool)))
+        .collect()
+}
+
+fn resolve(tool: Tool, bundled_dir: Option<&Path>, custom: Option<&Path>) -> ToolInfo {
+    let mut info = ToolInfo {
+        name: tool.name(),
+        tool,
+        available: false,
+        source: None,
+        path: None,
+        version: None,
+        problem: None,
+    };
+
+    let candidates = custom
+        .map(|path| (ToolSource::Custom, path.to_path_buf()))
+        .into_iter()
+        .chain(bundled_dir.map(|dir| (ToolSource::Bundled, dir.join(tool.file_name()))));
+    for (source, path) in candidates {
+        match check(&path) {
+            Ok(version) => {
+                info.available = true;
+                info.source = Some(source);
+                info.path = Some(path);
+                info.version = Some(version);
+                return info;
+            }
+            Err(problem) => {
+                info.problem.get_or_insert(problem);
+            }
+        }
+    }
+
+    if let Some((path, version)) = which(&tool.file_name())
+        .into_iter()
+        .find_map(|path| check(&path).ok().map(|version| (path, version)))
+    {
+        info.available = true;
+        info.source = Some(ToolSource::Path);
+        info.path = Some(path);
+        info.version = Some(version);
+    }
+    info
+}
+
+/// Make sure `path` is an executable that answers `-version`, and return
+/// the version it reports.
+fn check(path: &Path) -> Result<String, String> {
+    if !path.is_file() {
+        return Err(format!("{} not found", path.display()));
+    }
+    if !is_executable(path) {
+        return Err(format!("{} is not executable", path.display()));
+    }
+
+    let mut command = Command::new(path);
+    command
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(crate::sidecar::CREATE_NO_WINDOW);
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {e}", path.display()))?;
+    let deadline = Instant::now() + VERSION_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(20)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{} -version did not finish", path.display()));
+            }
+        }
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to read {} output: {e}", path.display()))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} -version exited with {}",
+            path.display(),
+            output.status
+        ));
+    }
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| format!("Unrecognized {} -version output", path.display()))
+}
+
+/// Pull the version out of `-version` output.
+///
+/// ffmpeg and ffprobe print `ffmpeg version 7.1 Copyright …` (`n7.1` on
+/// some distros, `N-117580-g5ad4d0c5a5` for git snapshots); img2webp
+/// prints `WebP Encoder version: 1.4.0` or just `1.4.0`.
+fn parse_version(output: &str) -> Option<String> {
+    let first = output.lines().find(|line| !line.trim().is_empty())?;
+    let version = match first.split_once("version") {
+        Some((_, rest)) => rest.trim_start_matches(':').split_whitespace().next(),
+        None => first.split_whitespace().next(),
+    }?;
+    let release = version.strip_prefix(['n', 'N']).unwrap_or(version);
+    let known = release.starts_with(|c: char| c.is_ascii_digit()) || version.starts_with("N-");
+    known.then(|| version.to_string())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|meta| meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+/// Every `file_name` on `PATH`, in lookup order.
+fn which(file_name: &str) -> Vec<PathBuf> {
+    std::env::var_os("PATH")
+        .map(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(file_name))
+                .filter(|path| path.is_file())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ffmpeg_releases() {
+        let output = "ffmpeg version 7.1 Copyright (c) 2000-2024 the FFmpeg developers\n\
+                      built with Apple clang version 16.0.0\n";
+        assert_eq!(parse_version(output).as_deref(), Some("7.1"));
+        let output = "ffprobe version 6.1.1-3ubuntu5 Copyright (c) 2007-2023\n";
+        assert_eq!(parse_version(output).as_deref(), Some("6.1.1-3ubuntu5"));
+        let output = "ffmpeg version n7.1 Copyright (c) 2000-2024\n";
+        assert_eq!(parse_version(output).as_deref(), Some("n7.1"));
+    }
+
+    #[test]
+    fn ffmpeg_git_snapshots() {
+        let output = "ffmpeg version N-117580-g5ad4d0c5a5-20241031 Copyright (c) 2000-2024\n";
+        assert_eq!(
+            parse_version(output).as_deref(),
+            Some("N-117580-g5ad4d0c5a5-20241031")
+        );
+    }
+
+    #[test]
+    fn img2webp_versions() {
+        let output = "WebP Encoder version: 1.4.0\nlibsharpyuv: 0.4.0\n";
+        assert_eq!(parse_version(output).as_deref(), Some("1.4.0"));
+        assert_eq!(parse_version("\n1.4.0\n").as_deref(), Some("1.4.0"));
+    }
+
+    #[test]
+    fn unrecognized_output() {
+        assert_eq!(parse_version(""), None);
+        assert_eq!(parse_version("  \n\n"), None);
+        assert_eq!(
+            parse_version("Usage: img2webp [file_options] [[frame_options] frame_file]..."),
+            None
+        );
+        assert_eq!(parse_version("ffmpeg version : unknown"), None);
+    }
+}
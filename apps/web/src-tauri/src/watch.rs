@@ -0,0 +1,471 @@
+//! Watch folders: every new file matching a pattern in a folder is
+//! processed, its result saved to an output folder, and the original
+//! kept, moved aside or deleted.
+//!
+//! Watches are kept in `watches.json` in the app config dir and started
+//! with the app. Files already in the folder when a watch starts are
+//! picked up too, unless the history shows their result was saved. A file
+//! is only submitted once its size and modification time have stopped
+//! changing, so copies and downloads in progress aren't picked up half
+//! written. Outcomes are announced through `watch-processed` and
+//! `watch-failed`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+use crate::history::JobHistory;
+use crate::jobs::{self, Submitted};
+use crate::json_file;
+use crate::pipelines::Engine;
+use crate::presets;
+use crate::processors;
+use crate::results::{self, OnConflict, SaveMode, SaveOptions};
+use crate::sidecar;
+
+/// How long a file must stay unchanged before it is submitted.
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+/// How often pending files are checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Where `move` puts originals, inside the watched folder.
+const PROCESSED_DIR: &str = "processed";
+
+/// What happens to a file once its result is saved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostAction {
+    #[default]
+    Keep,
+    /// Into a `processed` folder next to it.
+    Move,
+    Delete,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WatchFolder {
+    /// Empty when adding a watch; one is assigned.
+    #[serde(default)]
+    pub id: String,
+    pub dir: PathBuf,
+    /// Glob matched against file names, e.g. `*.mp4`. Hidden files are
+    /// always skipped.
+    #[serde(default = "default_pattern")]
+    pub pattern: String,
+    pub processor_id: String,
+    /// Options of this preset, if any, with `options` on top.
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    #[serde(default)]
+    pub options: Map<String, Value>,
+    pub output_dir: PathBuf,
+    /// Defaults to `{stem}_{processor}.{ext}`.
+    #[serde(default)]
+    pub naming_template: Option<String>,
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+    #[serde(default)]
+    pub post_action: PostAction,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_pattern() -> String {
+    "*".to_string()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// A watch and whether it is running, as returned by `list_watches`.
+#[derive(Debug, Serialize)]
+pub struct WatchStatus {
+    #[serde(flatten)]
+    pub watch: WatchFolder,
+    pub running: bool,
+    /// Why the watch couldn't start, e.g. its folder is gone.
+    pub error: Option<String>,
+}
+
+/// Payload of `watch-processed` and `watch-failed`.
+#[derive(Clone, Debug, Serialize)]
+struct WatchEvent {
+    watch_id: String,
+    input: PathBuf,
+    /// The saved result, for `watch-processed`.
+    output: Option<PathBuf>,
+    /// What went wrong, for `watch-failed`. The original is left alone.
+    reason: Option<String>,
+}
+
+/// A started watch; dropping the watcher stops its notifications and
+/// aborting the task cancels the files being processed.
+struct Running {
+    _watcher: RecommendedWatcher,
+    task: JoinHandle<()>,
+}
+
+/// Watches loaded from disk and the ones running, managed as Tauri state.
+pub struct WatchStore {
+    path: PathBuf,
+    watches: Mutex<Vec<WatchFolder>>,
+    running: Mutex<HashMap<String, Running>>,
+    errors: Mutex<HashMap<String, String>>,
+}
+
+impl WatchStore {
+    /// Load `watches.json` from `dir`. An unreadable file is moved aside
+    /// to `watches.json.bak` so the next save doesn't silently replace it.
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join("watches.json");
+        let watches = json_file::load(&path, |text| {
+            serde_json::from_str(text).map_err(|e| format!("Invalid watches.json: {e}"))
+        })
+        .unwrap_or_default();
+        Self {
+            path,
+            watches: Mutex::new(watches),
+            running: Mutex::new(HashMap::new()),
+            errors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn list(&self) -> Vec<WatchStatus> {
+        let running = self.running.lock().unwrap();
+        let errors = self.errors.lock().unwrap();
+        self.watches
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|watch| WatchStatus {
+                watch: watch.clone(),
+                running: running.contains_key(&watch.id),
+                error: errors.get(&watch.id).cloned(),
+            })
+            .collect()
+    }
+
+    fn save(&self, watches: &[WatchFolder]) -> Result<(), String> {
+        json_file::write(&self.path, &watches)
+    }
+
+    fn stop(&self, id: &str) {
+        if let Some(running) = self.running.lock().unwrap().remove(id) {
+            running.task.abort();
+        }
+        self.errors.lock().unwrap().remove(id);
+    }
+}
+
+/// Start every enabled watch. Called once on launch.
+pub fn start_all(app: &AppHandle) {
+    let watches = app.state::<WatchStore>().watches.lock().unwrap().clone();
+    for watch in watches.iter().filter(|watch| watch.enabled) {
+        restart(app, watch);
+    }
+}
+
+/// Validate and store a watch, then (re)start it if enabled.
+pub async fn save(app: &AppHandle, mut watch: WatchFolder) -> Result<WatchStatus, String> {
+    check(app, &watch).await?;
+    if watch.id.is_empty() {
+        watch.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let store = app.state::<WatchStore>();
+    {
+        let mut watches = store.watches.lock().unwrap();
+        let mut updated = watches.clone();
+        match updated.iter().position(|w| w.id == watch.id) {
+            Some(index) => updated[index] = watch.clone(),
+            None => updated.push(watch.clone()),
+        }
+        store.save(&updated)?;
+        *watches = updated;
+    }
+
+    if watch.enabled {
+        restart(app, &watch);
+    } else {
+        store.stop(&watch.id);
+    }
+    store
+        .list()
+        .into_iter()
+        .find(|status| status.watch.id == watch.id)
+        .ok_or_else(|| format!("No watch {}", watch.id))
+}
+
+/// Stop and forget a watch. Returns `false` if there was none.
+pub fn remove(app: &AppHandle, id: &str) -> Result<bool, String> {
+    let store = app.state::<WatchStore>();
+    {
+        let mut watches = store.watches.lock().unwrap();
+        let Some(index) = watches.iter().position(|watch| watch.id == id) else {
+            return Ok(false);
+        };
+        let mut updated = watches.clone();
+        updated.remove(index);
+        store.save(&updated)?;
+        *watches = updated;
+    }
+    // `list` takes `watches` last, so it must be released before `stop`.
+    store.stop(id);
+    Ok(true)
+}
+
+async fn check(app: &AppHandle, watch: &WatchFolder) -> Result<(), String> {
+    if !watch.dir.is_dir() {
+        return Err(format!("{} is not a folder", watch.dir.display()));
+    }
+    if !watch.output_dir.is_dir() {
+        return Err(format!("{} is not a folder", watch.output_dir.display()));
+    }
+    // Results saved into the watched folder would be processed in turn
+    if watch.output_dir.canonicalize().ok() == watch.dir.canonicalize().ok() {
+        return Err("The output folder must differ from the watched folder".to_string());
+    }
+    glob::Pattern::new(&watch.pattern)
+        .map_err(|e| format!("Invalid pattern {}: {e}", watch.pattern))?;
+    let processor = processors::fetch(app, &watch.processor_id).await?;
+    processor.check_options(&watch.options)?;
+    if let Some(preset_id) = &watch.preset_id {
+        let preset = app
+            .state::<presets::PresetStore>()
+            .get(preset_id)
+            .ok_or_else(|| format!("No preset {preset_id}"))?;
+        if preset.processor_id != watch.processor_id {
+            return Err(format!("Preset {} is for another processor", preset.name));
+        }
+    }
+    Ok(())
+}
+
+/// Start `watch`, recording why if it can't be.
+fn restart(app: &AppHandle, watch: &WatchFolder) {
+    let store = app.state::<WatchStore>();
+    store.stop(&watch.id);
+    match start(app, watch) {
+        Ok(running) => {
+            store
+                .running
+                .lock()
+                .unwrap()
+                .insert(watch.id.clone(), running);
+        }
+        Err(reason) => {
+            store
+                .errors
+                .lock()
+                .unwrap()
+                .insert(watch.id.clone(), reason);
+        }
+    }
+}
+
+fn start(app: &AppHandle, watch: &WatchFolder) -> Result<Running, String> {
+    let pattern = glob::Pattern::new(&watch.pattern)
+        .map_err(|e| format!("Invalid pattern {}: {e}", watch.pattern))?;
+    let (tx, rx) = mpsc::unbounded_channel();
+    let watched = pattern.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        for path in event.paths {
+            if matches(&watched, &path) {
+                let _ = tx.send(path);
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to watch {}: {e}", watch.dir.display()))?;
+    watcher
+        .watch(&watch.dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {}: {e}", watch.dir.display()))?;
+
+    // Watching first, so files arriving during the scan aren't missed
+    let existing = scan(app, &watch.dir, &pattern)?;
+    let task = tauri::async_runtime::spawn(run(app.clone(), watch.clone(), existing, rx));
+    Ok(Running {
+        _watcher: watcher,
+        task,
+    })
+}
+
+/// Whether `path` is a visible file name matching `pattern`.
+fn matches(pattern: &glob::Pattern, path: &Path) -> bool {
+    path.file_name().is_some_and(|name| {
+        let name = name.to_string_lossy();
+        !name.starts_with('.') && pattern.matches(&name)
+    })
+}
+
+/// The matching files already in `dir`, except those whose result was
+/// saved and that haven't changed since.
+fn scan(app: &AppHandle, dir: &Path, pattern: &glob::Pattern) -> Result<Vec<PathBuf>, String> {
+    let history = app.state::<JobHistory>();
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {e}", dir.display()))?;
+    Ok(entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file() && matches(pattern, path))
+        .filter(|path| {
+            // The history has the paths as submitted, i.e. canonical
+            let saved = path
+                .canonicalize()
+                .ok()
+                .and_then(|path| history.saved_result(&path));
+            saved.is_none()
+        })
+        .collect())
+}
+
+/// Size and modification time of a file, to tell when it stops changing.
+type Snapshot = (u64, Option<SystemTime>);
+
+fn snapshot(path: &Path) -> Option<Snapshot> {
+    let meta = fs::metadata(path).ok()?;
+    meta.is_file().then(|| (meta.len(), meta.modified().ok()))
+}
+
+/// Collect `existing` and notified files until they settle, then process
+/// them. Aborting this task cancels the files being processed.
+async fn run(
+    app: AppHandle,
+    watch: WatchFolder,
+    existing: Vec<PathBuf>,
+    mut events: mpsc::UnboundedReceiver<PathBuf>,
+) {
+    // Last snapshot of each pending file and since when it's unchanged
+    let mut pending: HashMap<PathBuf, (Option<Snapshot>, Instant)> = existing
+        .into_iter()
+        .map(|path| (path, (None, Instant::now())))
+        .collect();
+    // What each file looked like when it was last submitted
+    let mut handled: HashMap<PathBuf, Snapshot> = HashMap::new();
+    // Dropped with this task, which aborts them
+    let mut processing = JoinSet::new();
+    let mut tick = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            path = events.recv() => match path {
+                Some(path) => {
+                    pending.insert(path, (None, Instant::now()));
+                }
+                None => return,
+            },
+            Some(_) = processing.join_next(), if !processing.is_empty() => {}
+            _ = tick.tick() => {
+                let mut settled = Vec::new();
+                pending.retain(|path, (last, since)| {
+                    let Some(current) = snapshot(path) else {
+                        return false;
+                    };
+                    if *last != Some(current) {
+                        *last = Some(current);
+                        *since = Instant::now();
+                        return true;
+                    }
+                    if since.elapsed() < SETTLE_TIME {
+                        return true;
+                    }
+                    if handled.get(path) != Some(&current) {
+                        settled.push((path.clone(), current));
+                    }
+                    false
+                });
+                for (path, current) in settled {
+                    handled.insert(path.clone(), current);
+                    let app = app.clone();
+                    let watch = watch.clone();
+                    processing.spawn(async move {
+                        let (output, reason) = match process(&app, &watch, &path).await {
+                            Ok(output) => (Some(output), None),
+                            Err(reason) => (None, Some(reason)),
+                        };
+                        let event = if output.is_some() { "watch-processed" } else { "watch-failed" };
+                        let _ = app.emit(
+                            event,
+                            WatchEvent {
+                                watch_id: watch.id.clone(),
+                                input: path,
+                                output,
+                                reason,
+                            },
+                        );
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Run one file through the watch's processor, save the result and deal
+/// with the original. Returns where the result was saved.
+async fn process(app: &AppHandle, watch: &WatchFolder, path: &Path) -> Result<PathBuf, String> {
+    sidecar::wait_ready(app).await?;
+    let mut options = match &watch.preset_id {
+        Some(preset_id) => presets::apply(app, preset_id).await?.options,
+        None => Map::new(),
+    };
+    options.extend(watch.options.clone());
+
+    let submitted = jobs::submit(
+        app,
+        watch.processor_id.clone(),
+        vec![path.to_path_buf()],
+        Value::Object(options),
+    )
+    .await?;
+    let (Submitted::Job { id } | Submitted::Batch { id, .. }) = submitted;
+    let job = Engine::App(app).wait_for_job(&id, |_| {}).await?;
+    if job.status == "failed" {
+        return Err(job.error.unwrap_or_else(|| "Job failed".to_string()));
+    }
+
+    let save = SaveOptions {
+        template: watch.naming_template.clone(),
+        on_conflict: watch.on_conflict,
+        mode: SaveMode::Move,
+    };
+    let saved = results::save_job(app, &id, &watch.output_dir, &save).await?;
+    let output = saved.path.ok_or_else(|| {
+        saved
+            .skipped
+            .unwrap_or_else(|| "Result not saved".to_string())
+    })?;
+
+    match watch.post_action {
+        PostAction::Keep => {}
+        PostAction::Move => {
+            let dir = watch.dir.join(PROCESSED_DIR);
+            fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+            let mut dest = dir.join(path.file_name().unwrap_or_default());
+            if dest.exists() {
+                dest = results::free_name(&dest);
+            }
+            results::move_file(path, &dest)
+                .map_err(|e| format!("Failed to move {}: {e}", path.display()))?;
+        }
+        PostAction::Delete => {
+            fs::remove_file(path)
+                .map_err(|e| format!("Failed to delete {}: {e}", path.display()))?;
+        }
+    }
+    Ok(output)
+}
@@ -1,4 +1,5 @@
 let _apiBaseUrl: string | null = null;
+let _apiToken: string | null = null;
 
 /** True when running inside the Tauri desktop shell. */
 export function isTauri(): boolean {
@@ -7,7 +8,8 @@ export function isTauri(): boolean {
 
 /**
  * Detect the API base URL.
- * - Desktop (Tauri): ask Rust for the sidecar port via IPC.
+ * - Desktop (Tauri): ask Rust for the sidecar port and per-launch token via IPC.
+ *   With the Unix socket transport, requests go through the `vimix-api://` proxy.
  * - Web: use the env variable or default 8787.
  */
 export async function initApiUrl(): Promise<string> {
@@ -16,8 +18,19 @@ export async function initApiUrl(): Promise<string> {
   if (isTauri() && !import.meta.env.DEV) {
     try {
       const { invoke } = await import("@tauri-apps/api/core");
-      const port = await invoke<number>("get_api_port");
-      _apiBaseUrl = `http://127.0.0.1:${port}`;
+      const creds = await invoke<{
+        port: number | null;
+        token: string;
+        transport: "tcp" | "unix";
+      }>("get_api_credentials");
+      _apiToken = creds.token;
+      if (creds.transport === "unix") {
+        _apiBaseUrl = "vimix-api://localhost";
+        return _apiBaseUrl;
+      }
+      // Without a port the sidecar is still starting; waitForBackend sets it once ready
+      if (creds.port === null) return API_URL();
+      _apiBaseUrl = `http://127.0.0.1:${creds.port}`;
     } catch {
       // Fallback if IPC fails
       _apiBaseUrl = import.meta.env.VITE_API_URL || "http://localhost:8787";
@@ -33,16 +46,95 @@ function API_URL(): string {
   return _apiBaseUrl || import.meta.env.VITE_API_URL || "http://localhost:8787";
 }
 
+/**
+ * Point the API at a new sidecar port.
+ * Called when the desktop supervisor restarts the sidecar on a different port.
+ * `null` (Unix socket transport) keeps the proxy URL.
+ */
+export function setApiPort(port: number | null): void {
+  if (port === null) return;
+  _apiBaseUrl = `http://127.0.0.1:${port}`;
+}
+
 /** Returns the current resolved API base URL (after initApiUrl has been called). */
 export function getApiUrl(): string {
   return API_URL();
 }
 
 /**
- * Wait until the backend API responds to /health.
+ * `fetch` against the API, sending the desktop API token when there is one.
+ * `path` is relative to the API base URL (e.g. "/jobs").
+ */
+export function apiFetch(path: string, init: RequestInit = {}): Promise<Response> {
+  const headers = new Headers(init.headers);
+  if (_apiToken) headers.set("X-Vimix-Token", _apiToken);
+  return fetch(`${API_URL()}${path}`, { ...init, headers });
+}
+
+/**
+ * Absolute API URL carrying the token as a query parameter, for places
+ * that can't send headers (EventSource, <img>/<video> src).
+ */
+function apiUrlWithToken(path: string): string {
+  const url = `${API_URL()}${path}`;
+  return _apiToken ? `${url}?token=${encodeURIComponent(_apiToken)}` : url;
+}
+
+/** User settings stored by the desktop shell. */
+export interface Settings {
+  tools: {
+    ffmpeg: string | null;
+    ffprobe: string | null;
+    img2webp: string | null;
+  };
+  sidecar: {
+    host: string | null;
+    port: number | null;
+    transport: "tcp" | "unix";
+    extra_args: string[];
+    /** rembg models to use instead of the bundled ones. */
+    model_dir: string | null;
+  };
+}
+
+export async function getSettings(): Promise<Settings> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<Settings>("get_settings");
+}
+
+/**
+ * Save the settings. Rejects with the validation error if they are invalid;
+ * otherwise the sidecar restarts to apply them and `backend-ready` follows.
+ *
+ * The restart cancels running jobs and deletes unsaved results, so while
+ * jobs are running this rejects unless `force` is set.
+ */
+export async function updateSettings(
+  settings: Settings,
+  force = false,
+): Promise<Settings> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<Settings>("update_settings", { settings, force });
+}
+
+/** Sidecar lifecycle state, as reported by the desktop shell. */
+export type BackendStatus =
+  | { status: "starting" }
+  | { status: "ready"; port: number | null }
+  | { status: "failed"; reason: string };
+
+/**
+ * Wait until the backend API is ready.
  * Used by the loading screen to know when the sidecar is ready.
+ * - Desktop (Tauri): Rust probes the sidecar and reports `backend-ready`
+ *   or `backend-failed`; a failure rejects with the sidecar's reason.
+ * - Dev / web: poll /health once a second.
  */
 export async function waitForBackend(maxAttempts = 60): Promise<void> {
+  if (isTauri() && !import.meta.env.DEV) {
+    return waitForDesktopBackend();
+  }
+
   for (let i = 0; i < maxAttempts; i++) {
     try {
       const res = await fetch(`${API_URL()}/health`);
@@ -55,6 +147,50 @@ export async function waitForBackend(maxAttempts = 60): Promise<void> {
   throw new Error("Backend did not start in time");
 }
 
+/** Ask the desktop shell to start the sidecar again after a failed start. */
+export async function restartBackend(): Promise<void> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  await invoke<boolean>("restart_backend");
+}
+
+async function waitForDesktopBackend(): Promise<void> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  const { listen } = await import("@tauri-apps/api/event");
+
+  return new Promise<void>((resolve, reject) => {
+    const unlisteners: (() => void)[] = [];
+    let settled = false;
+
+    function settle(status: BackendStatus) {
+      if (settled || status.status === "starting") return;
+      settled = true;
+      unlisteners.forEach((fn) => fn());
+      if (status.status === "ready") {
+        setApiPort(status.port);
+        resolve();
+      } else {
+        reject(new Error(status.reason));
+      }
+    }
+
+    // Subscribe first, then read the current status, so neither can be missed
+    Promise.all([
+      listen<{ port: number | null }>("backend-ready", (e) =>
+        settle({ status: "ready", port: e.payload.port }),
+      ),
+      listen<{ reason: string }>("backend-failed", (e) =>
+        settle({ status: "failed", reason: e.payload.reason }),
+      ),
+    ])
+      .then(async (fns) => {
+        unlisteners.push(...fns);
+        if (settled) fns.forEach((fn) => fn());
+        settle(await invoke<BackendStatus>("get_backend_status"));
+      })
+      .catch(reject);
+  });
+}
+
 export interface OptionChoice {
   value: string;
   label: string;
@@ -127,6 +263,7 @@ export interface Job {
   id: string;
   processor_id: string;
   original_filename: string;
+  options: Record<string, unknown>;
   status: "pending" | "processing" | "completed" | "failed";
   progress: number;
   message: string;
@@ -153,7 +290,7 @@ export interface BatchWithJobs extends Batch {
 }
 
 export async function fetchProcessors(): Promise<Processor[]> {
-  const res = await fetch(`${API_URL()}/processors`);
+  const res = await apiFetch("/processors");
   if (!res.ok) throw new Error("Failed to fetch processors");
   return res.json();
 }
@@ -168,7 +305,7 @@ export async function createJob(
   form.append("file", file);
   form.append("options", JSON.stringify(options));
 
-  const res = await fetch(`${API_URL()}/jobs`, { method: "POST", body: form });
+  const res = await apiFetch("/jobs", { method: "POST", body: form });
   if (!res.ok) {
     const err = await res.json().catch(() => ({ detail: "Upload failed" }));
     throw new Error(err.detail || "Upload failed");
@@ -177,7 +314,7 @@ export async function createJob(
 }
 
 export async function fetchJob(jobId: string): Promise<Job> {
-  const res = await fetch(`${API_URL()}/jobs/${jobId}`);
+  const res = await apiFetch(`/jobs/${jobId}`);
   if (!res.ok) throw new Error("Job not found");
   return res.json();
 }
@@ -187,7 +324,9 @@ export function subscribeProgress(
   onEvent: (e: ProgressEvent) => void,
   onDone: () => void,
 ): () => void {
-  const es = new EventSource(`${API_URL()}/jobs/${jobId}/progress`);
+  if (isTauri() && !import.meta.env.DEV) return followProgress(jobId, onEvent, onDone);
+
+  const es = new EventSource(apiUrlWithToken(`/jobs/${jobId}/progress`));
 
   es.onmessage = (msg) => {
     const data: ProgressEvent = JSON.parse(msg.data);
@@ -206,6 +345,63 @@ export function subscribeProgress(
   return () => es.close();
 }
 
+/** Latest state of a job followed by the desktop app. */
+export interface JobProgress extends ProgressEvent {
+  id: string;
+  processor_id: string | null;
+  original_filename: string | null;
+}
+
+/**
+ * Desktop: the app follows each job once and broadcasts its progress to every
+ * view through `job-progress` / `job-finished` events.
+ */
+function followProgress(
+  jobId: string,
+  onEvent: (e: ProgressEvent) => void,
+  onDone: () => void,
+): () => void {
+  let stopped = false;
+  const unlisteners: (() => void)[] = [];
+  const stop = () => {
+    stopped = true;
+    unlisteners.forEach((fn) => fn());
+  };
+
+  (async () => {
+    const { invoke } = await import("@tauri-apps/api/core");
+    const { listen } = await import("@tauri-apps/api/event");
+    const fns = await Promise.all([
+      listen<JobProgress>("job-progress", (e) => {
+        if (e.payload.id === jobId) onEvent(e.payload);
+      }),
+      listen<JobProgress>("job-finished", (e) => {
+        if (e.payload.id !== jobId || stopped) return;
+        onEvent(e.payload);
+        stop();
+        onDone();
+      }),
+    ]);
+    unlisteners.push(...fns);
+    if (stopped) return stop();
+    try {
+      await invoke("track_job", { jobId });
+    } catch {
+      stop();
+      onDone();
+    }
+  })();
+
+  return stop;
+}
+
+/** Desktop only: jobs the app is following that haven't finished yet. */
+export async function listActiveJobs(): Promise<JobProgress[]> {
+  if (!isTauri()) return [];
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<JobProgress[]>("list_active_jobs");
+}
+
 export async function createBatch(
   processorId: string,
   files: File[],
@@ -218,7 +414,7 @@ export async function createBatch(
   }
   form.append("options", JSON.stringify(options));
 
-  const res = await fetch(`${API_URL()}/jobs/batch`, { method: "POST", body: form });
+  const res = await apiFetch("/jobs/batch", { method: "POST", body: form });
   if (!res.ok) {
     const err = await res.json().catch(() => ({ detail: "Upload failed" }));
     throw new Error(err.detail || "Upload failed");
@@ -226,14 +422,306 @@ export async function createBatch(
   return res.json();
 }
 
+/**
+ * Desktop only: process files that are already on disk, by path.
+ * The sidecar reads them in place instead of receiving an upload.
+ */
+export async function submitJob(
+  processorId: string,
+  paths: string[],
+  options: Record<string, unknown> = {},
+): Promise<{ type: "job"; id: string } | { type: "batch"; id: string; job_ids: string[] }> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke("submit_job", { processorId, paths, options });
+}
+
+/** A file dropped onto or opened with the app, as reported by the desktop shell. */
+export interface InputFile {
+  path: string;
+  name: string;
+  size: number;
+  mime: string | null;
+  /** Ids of the processors that accept this file. */
+  processors: string[];
+}
+
+/**
+ * Desktop only: files the app was opened with ("Open with", command line)
+ * that haven't been handled yet. New ones are announced by `files-opened`.
+ */
+export async function takePendingInputs(): Promise<InputFile[]> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<InputFile[]>("take_pending_inputs");
+}
+
+/** A `vimix://process` link waiting for the user to confirm it. */
+export interface DeepLinkRequest {
+  id: string;
+  processor_id: string;
+  processor_label: string;
+  files: string[];
+  options: Record<string, unknown>;
+}
+
+/**
+ * Desktop only: `vimix://` links not confirmed or dismissed yet.
+ * New ones are announced by `deep-link-request`.
+ */
+export async function getPendingDeepLinks(): Promise<DeepLinkRequest[]> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<DeepLinkRequest[]>("get_pending_deep_links");
+}
+
+/** Desktop only: start the job a `vimix://` link asked for. */
+export async function confirmDeepLink(
+  id: string,
+): Promise<{ type: "job"; id: string } | { type: "batch"; id: string; job_ids: string[] }> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke("confirm_deep_link", { id });
+}
+
+/** Desktop only: discard a `vimix://` link the user turned down. */
+export async function dismissDeepLink(id: string): Promise<boolean> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<boolean>("dismiss_deep_link", { id });
+}
+
+/** A job as recorded in the desktop app's history. */
+export interface JobRecord {
+  id: string;
+  batch_id: string | null;
+  processor_id: string;
+  /** Absolute paths when `local`, file names for uploads. */
+  inputs: string[];
+  local: boolean;
+  options: Record<string, unknown>;
+  status: "pending" | "processing" | "completed" | "failed";
+  /** Where the result was saved; `null` until it is. */
+  output_path: string | null;
+  error: string | null;
+  created_at: string;
+  started_at: string | null;
+  finished_at: string | null;
+  duration_ms: number | null;
+}
+
+export interface HistoryFilter {
+  processor_id?: string;
+  status?: string;
+  /** Part of an input or output path. */
+  search?: string;
+  /** RFC 3339 timestamps or `YYYY-MM-DD` dates; `until` is exclusive. */
+  since?: string;
+  until?: string;
+}
+
+/** Desktop only: a page (0-based) of past jobs, newest first. */
+export async function listJobHistory(
+  filter: HistoryFilter = {},
+  page = 0,
+): Promise<{ records: JobRecord[]; total: number }> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke("list_job_history", { filter, page });
+}
+
+/** Desktop only: the history record of one job, `null` if there is none. */
+export async function getJobRecord(jobId: string): Promise<JobRecord | null> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<JobRecord | null>("get_job_record", { jobId });
+}
+
+/**
+ * Desktop only: run a past job again on the same files, with its options and
+ * `optionOverrides` on top (`null` removes an option). Fails if the job was an
+ * upload or its files are gone or changed.
+ */
+export async function rerunJob(
+  jobId: string,
+  optionOverrides: Record<string, unknown> = {},
+): Promise<{ type: "job"; id: string } | { type: "batch"; id: string; job_ids: string[] }> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke("rerun_job", { jobId, optionOverrides });
+}
+
+/** Desktop only: forget a past job. Saved results are kept. */
+export async function deleteJobRecord(jobId: string): Promise<boolean> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<boolean>("delete_job_record", { jobId });
+}
+
+/** Named options for a processor, stored by the desktop app. */
+export interface Preset {
+  id: string;
+  name: string;
+  processor_id: string;
+  options: Record<string, unknown>;
+}
+
+/**
+ * Desktop only: save a preset, checked against the processor's schema.
+ * Pass `id` to update one; an existing name for the processor is replaced.
+ */
+export async function savePreset(
+  name: string,
+  processorId: string,
+  options: Record<string, unknown>,
+  id?: string,
+): Promise<Preset> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<Preset>("save_preset", { id, name, processorId, options });
+}
+
+/** Desktop only: presets of one processor, or all of them, by name. */
+export async function listPresets(processorId?: string): Promise<Preset[]> {
+  if (!isTauri()) return [];
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<Preset[]>("list_presets", { processorId });
+}
+
+export async function deletePreset(id: string): Promise<boolean> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<boolean>("delete_preset", { id });
+}
+
+/**
+ * Desktop only: a preset's options as the processor takes them today.
+ * Options it no longer accepts are left out and explained in `skipped`.
+ */
+export async function applyPreset(
+  id: string,
+): Promise<{ processor_id: string; options: Record<string, unknown>; skipped: string[] }> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke("apply_preset", { id });
+}
+
+/** Desktop only: write presets (all when `ids` is empty) to a JSON file. */
+export async function exportPresets(path: string, ids: string[] = []): Promise<number> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<number>("export_presets", { path, ids });
+}
+
+/** Desktop only: add the presets from an exported file; all must be valid. */
+export async function importPresets(path: string): Promise<Preset[]> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<Preset[]>("import_presets", { path });
+}
+
+/** A folder whose new files the desktop app processes automatically. */
+export interface WatchFolder {
+  /** Leave empty to add a new watch. */
+  id: string;
+  dir: string;
+  /** Glob matched against file names, e.g. `*.mp4`. */
+  pattern: string;
+  processor_id: string;
+  preset_id: string | null;
+  options: Record<string, unknown>;
+  output_dir: string;
+  naming_template: string | null;
+  on_conflict: "overwrite" | "rename" | "skip";
+  /** What happens to the original once its result is saved. */
+  post_action: "keep" | "move" | "delete";
+  enabled: boolean;
+}
+
+export interface WatchStatus extends WatchFolder {
+  running: boolean;
+  error: string | null;
+}
+
+/** Desktop only: the watch folders and whether each is running. */
+export async function listWatches(): Promise<WatchStatus[]> {
+  if (!isTauri()) return [];
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<WatchStatus[]>("list_watches");
+}
+
+/**
+ * Desktop only: add or update a watch folder. Each processed file is announced
+ * by `watch-processed`, each failure by `watch-failed`.
+ */
+export async function saveWatch(watch: WatchFolder): Promise<WatchStatus> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<WatchStatus>("save_watch", { watch });
+}
+
+export async function removeWatch(id: string): Promise<boolean> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<boolean>("remove_watch", { id });
+}
+
+/** Processors run one after the other, each on the previous result. */
+export interface Pipeline {
+  /** Leave empty to add a new pipeline. */
+  id: string;
+  name: string;
+  steps: { processor_id: string; options: Record<string, unknown> }[];
+}
+
+export interface PipelineStep {
+  processor_id: string;
+  job_id: string | null;
+  status: "pending" | "running" | "completed" | "failed" | "skipped";
+  progress: number;
+  message: string;
+  error: string | null;
+  duration_ms: number | null;
+}
+
+/** One file going through a pipeline, the payload of `pipeline-progress`. */
+export interface PipelineRun {
+  id: string;
+  pipeline: string;
+  input: string;
+  status: "running" | "completed" | "failed";
+  /** Over all steps, 0 to 100. */
+  progress: number;
+  steps: PipelineStep[];
+  output: string | null;
+  error: string | null;
+}
+
+/** Desktop only: the pipelines, by name. */
+export async function listPipelines(): Promise<Pipeline[]> {
+  if (!isTauri()) return [];
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<Pipeline[]>("list_pipelines");
+}
+
+/** Desktop only: add or update a pipeline; every step's options are checked. */
+export async function savePipeline(pipeline: Pipeline): Promise<Pipeline> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<Pipeline>("save_pipeline", { pipeline });
+}
+
+export async function deletePipeline(id: string): Promise<boolean> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<boolean>("delete_pipeline", { id });
+}
+
+/**
+ * Desktop only: run each file through a pipeline and save the final results
+ * into `destDir`. Follow `pipeline-progress` while it runs.
+ */
+export async function runPipeline(
+  id: string,
+  paths: string[],
+  destDir: string,
+  namingTemplate?: string,
+  onConflict?: "overwrite" | "rename" | "skip",
+): Promise<PipelineRun[]> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<PipelineRun[]>("run_pipeline", { id, paths, destDir, namingTemplate, onConflict });
+}
+
 export async function fetchBatch(batchId: string): Promise<BatchWithJobs> {
-  const res = await fetch(`${API_URL()}/jobs/batch/${batchId}`);
+  const res = await apiFetch(`/jobs/batch/${batchId}`);
   if (!res.ok) throw new Error("Batch not found");
   return res.json();
 }
 
 export function getResultUrl(jobId: string): string {
-  return `${API_URL()}/jobs/${jobId}/result`;
+  return apiUrlWithToken(`/jobs/${jobId}/result`);
 }
 
 /**
@@ -259,3 +747,47 @@ export async function downloadResult(jobId: string, filename: string): Promise<v
   document.body.removeChild(a);
   URL.revokeObjectURL(objectUrl);
 }
+
+/** Desktop only: show a finished job's result in the system file manager. */
+export async function revealResult(jobId: string): Promise<void> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  await invoke("reveal_result", { jobId });
+}
+
+/** Desktop only: open a finished job's result with the default app. */
+export async function openResult(jobId: string): Promise<void> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  await invoke("open_result", { jobId });
+}
+
+/** Options for saving results straight into a folder (desktop only). */
+export interface SaveResultOptions {
+  /** Placeholders: {stem}, {name}, {ext}, {processor}, {job}, {index}. */
+  namingTemplate?: string;
+  onConflict?: "overwrite" | "rename" | "skip";
+  mode?: "copy" | "move";
+}
+
+export interface SavedResult {
+  job_id: string;
+  path: string | null;
+  skipped: string | null;
+}
+
+export async function saveJobResult(
+  jobId: string,
+  destDir: string,
+  options: SaveResultOptions = {},
+): Promise<SavedResult> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<SavedResult>("save_job_result", { jobId, destDir, ...options });
+}
+
+export async function saveBatchResults(
+  batchId: string,
+  destDir: string,
+  options: SaveResultOptions = {},
+): Promise<SavedResult[]> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return invoke<SavedResult[]>("save_batch_results", { batchId, destDir, ...options });
+}
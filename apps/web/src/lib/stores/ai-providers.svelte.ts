@@ -14,7 +14,7 @@ import {
   exchangeCodeForTokens,
   refreshAccessToken,
 } from "$lib/ai/oauth";
-import { getApiUrl } from "$lib/api";
+import { apiFetch } from "$lib/api";
 
 const PROVIDERS_KEY = "vimix-ai-providers";
 const MODEL_KEY = "vimix-selected-model";
@@ -331,7 +331,7 @@ export async function connectProviderOAuth(
   const state = generateState();
 
   // Tell backend to start the callback listener
-  const startRes = await fetch(`${getApiUrl()}/oauth/start`, {
+  const startRes = await apiFetch("/oauth/start", {
     method: "POST",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify({ state }),
@@ -359,7 +359,7 @@ export async function connectProviderOAuth(
   for (let i = 0; i < maxAttempts; i++) {
     await new Promise((r) => setTimeout(r, 2000));
     try {
-      const pollRes = await fetch(`${getApiUrl()}/oauth/poll/${state}`);
+      const pollRes = await apiFetch(`/oauth/poll/${state}`);
       if (!pollRes.ok) break;
       const data = await pollRes.json();
       if (data.status === "received" && data.code) {
@@ -373,7 +373,7 @@ export async function connectProviderOAuth(
 
   if (!code) {
     // Clean up the listener
-    await fetch(`${getApiUrl()}/oauth/stop`, { method: "POST" }).catch(() => {});
+    await apiFetch("/oauth/stop", { method: "POST" }).catch(() => {});
     return { success: false, error: "timeout" };
   }
 
@@ -13,6 +13,8 @@ Usage:
 
 Environment variables:
     VIMIX_API_URL   — backend URL (default: http://localhost:8787)
+    VIMIX_API_TOKEN — backend API token (set by the desktop app, inherited by this process)
+    VIMIX_API_SOCKET — Unix socket path, used instead of a TCP port when set
     VIMIX_MCP_PORT  — server port (default: 8788)
 """
 
@@ -32,6 +34,8 @@ from mcp.server.fastmcp import FastMCP
 logger = logging.getLogger("vimix.mcp")
 
 VIMIX_API_URL = os.environ.get("VIMIX_API_URL", "http://localhost:8787")
+VIMIX_API_TOKEN = os.environ.get("VIMIX_API_TOKEN")
+VIMIX_API_SOCKET = os.environ.get("VIMIX_API_SOCKET")
 VIMIX_MCP_PORT = int(os.environ.get("VIMIX_MCP_PORT", "8788"))
 POLL_INTERVAL = 2  # seconds
 POLL_TIMEOUT = 300  # 5 minutes
@@ -53,6 +57,8 @@ mcp = FastMCP(
 def _client() -> httpx.AsyncClient:
     return httpx.AsyncClient(
         base_url=VIMIX_API_URL,
+        headers={"X-Vimix-Token": VIMIX_API_TOKEN} if VIMIX_API_TOKEN else None,
+        transport=httpx.AsyncHTTPTransport(uds=VIMIX_API_SOCKET) if VIMIX_API_SOCKET else None,
         timeout=httpx.Timeout(connect=10, read=120, write=120, pool=10),
     )
 
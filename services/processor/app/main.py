@@ -10,11 +10,17 @@ import sys
 os.environ.setdefault("NUMBA_NUM_THREADS", "1")
 
 import asyncio
+import hmac
+import json
 import logging
+import re
+import socket
 import subprocess
 from contextlib import asynccontextmanager
 
-from fastapi import FastAPI
+import uvicorn
+from fastapi import FastAPI, HTTPException, Request
+from fastapi.responses import JSONResponse
 
 # When running as a PyInstaller bundle, point rembg to bundled models
 if getattr(sys, "frozen", False):
@@ -121,6 +127,11 @@ async def lifespan(app: FastAPI):
     task = asyncio.create_task(_cleanup_loop())
     yield
     task.cancel()
+    # Drain: stop running jobs and leave no job files behind
+    await jobs.cancel_running_jobs()
+    for job_id in job_manager.job_ids():
+        cleanup_job(job_id)
+        job_manager.remove_job(job_id)
     _stop_mcp_server()
 
 
@@ -130,6 +141,38 @@ app = FastAPI(
     lifespan=lifespan,
 )
 
+# Per-launch secret set by the desktop app. When present, every request
+# except the health probe must carry it, so other local processes and
+# browser tabs can't drive the API.
+API_TOKEN = os.environ.get("VIMIX_API_TOKEN")
+_PUBLIC_PATHS = {"/health"}
+
+
+@app.middleware("http")
+async def require_token(request: Request, call_next):
+    """Reject requests without the API token (header or `token` query param)."""
+    if API_TOKEN and request.method != "OPTIONS" and request.url.path not in _PUBLIC_PATHS:
+        supplied = request.headers.get("x-vimix-token") or request.query_params.get("token") or ""
+        if not hmac.compare_digest(supplied, API_TOKEN):
+            return JSONResponse(status_code=401, content={"detail": "Invalid or missing API token"})
+    return await call_next(request)
+
+
+class _RedactToken(logging.Filter):
+    """Hide the `token` query parameter in uvicorn's access log lines."""
+
+    _PARAM = re.compile(r"([?&]token=)[^&\s]*")
+
+    def filter(self, record: logging.LogRecord) -> bool:
+        if isinstance(record.args, tuple):
+            record.args = tuple(
+                self._PARAM.sub(r"\1[redacted]", arg) if isinstance(arg, str) else arg
+                for arg in record.args
+            )
+        return True
+
+
+# Added last so it wraps the token check and 401s still carry CORS headers
 app.add_middleware(
     CORSMiddleware,
     allow_origins=["*"],
@@ -147,6 +190,16 @@ async def health():
     return {"status": "ok"}
 
 
+@app.post("/shutdown")
+async def shutdown():
+    """Ask the server to exit gracefully (used by the desktop app on quit)."""
+    server: uvicorn.Server | None = getattr(app.state, "server", None)
+    if server is None:
+        raise HTTPException(status_code=409, detail="Server was not started from the CLI")
+    server.should_exit = True
+    return {"status": "shutting_down"}
+
+
 @app.delete("/cleanup")
 async def manual_cleanup():
     """Manually trigger cleanup of expired jobs (older than 1 hour)."""
@@ -157,14 +210,91 @@ async def manual_cleanup():
     return {"removed": len(expired)}
 
 
+def _bind_socket(host: str, port: int) -> socket.socket:
+    """Bind the listening socket up front so port 0 resolves to a real port."""
+    family = socket.AF_INET6 if ":" in host else socket.AF_INET
+    sock = socket.socket(family, socket.SOCK_STREAM)
+    if sys.platform != "win32":
+        sock.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
+    sock.bind((host, port))
+    return sock
+
+
+def _bind_unix_socket(path: str) -> socket.socket:
+    """Bind a Unix domain socket only the current user can connect to."""
+    if os.path.exists(path):
+        os.unlink(path)  # Stale socket from a previous run
+    sock = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+    sock.bind(path)
+    os.chmod(path, 0o600)
+    return sock
+
+
+def _report(event: str, **fields) -> None:
+    """Send a control message to the supervising desktop app over stdout."""
+    print("@vimix " + json.dumps({"event": event, **fields}), flush=True)
+
+
 if __name__ == "__main__":
     import argparse
 
-    import uvicorn
-
     parser = argparse.ArgumentParser(description="Vimix Processor API")
-    parser.add_argument("--port", type=int, default=8787, help="Port to listen on")
+    parser.add_argument(
+        "--port", type=int, default=8787, help="Port to listen on (0 picks a free port)"
+    )
     parser.add_argument("--host", type=str, default="127.0.0.1", help="Host to bind to")
+    parser.add_argument(
+        "--uds", type=str, default=None, help="Listen on this Unix socket instead of a TCP port"
+    )
+    parser.add_argument(
+        "--log-level",
+        choices=["debug", "info", "warning", "error"],
+        default="info",
+        help="Minimum level of log lines to print",
+    )
+    parser.add_argument(
+        "--supervised",
+        action="store_true",
+        help="Run under the desktop app: own process group, control messages on stdout",
+    )
     args = parser.parse_args()
 
-    uvicorn.run(app, host=args.host, port=args.port)
+    if args.supervised and hasattr(os, "setpgrp"):
+        # Lead a process group so the app can stop us together with the
+        # MCP server and any ffmpeg children, even if we hang.
+        os.setpgrp()
+        _report("process_group", pgid=os.getpgrp())
+
+    # Log as "LEVEL:name:message" on stderr; the desktop shell parses the prefix.
+    logging.basicConfig(
+        level=args.log_level.upper(), format="%(levelname)s:%(name)s:%(message)s"
+    )
+
+    if args.uds:
+        sock = _bind_unix_socket(args.uds)
+        port = None
+        if args.supervised:
+            _report("listening", socket=args.uds)
+        # The MCP server subprocess inherits this (and VIMIX_API_TOKEN) to reach us
+        os.environ.setdefault("VIMIX_API_URL", "http://localhost")
+        os.environ.setdefault("VIMIX_API_SOCKET", args.uds)
+    else:
+        sock = _bind_socket(args.host, args.port)
+        port = sock.getsockname()[1]
+        if args.supervised:
+            _report("listening", port=port)
+        # The MCP server subprocess inherits this (and VIMIX_API_TOKEN) to reach us
+        os.environ.setdefault("VIMIX_API_URL", f"http://{args.host}:{port}")
+
+    config = uvicorn.Config(
+        app, host=args.host, port=port or 0, uds=args.uds, log_level=args.log_level
+    )
+    # After the Config, which sets up uvicorn's loggers
+    logging.getLogger("uvicorn.access").addFilter(_RedactToken())
+    server = uvicorn.Server(config)
+    app.state.server = server
+    try:
+        server.run(sockets=[sock])
+    finally:
+        if args.uds and os.path.exists(args.uds):
+            os.unlink(args.uds)
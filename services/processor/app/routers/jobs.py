@@ -7,6 +7,7 @@ from typing import List, Optional
 
 from fastapi import APIRouter, UploadFile, Form, HTTPException
 from fastapi.responses import FileResponse, StreamingResponse
+from pydantic import BaseModel
 
 from app.processors.registry import get_processor
 from app.services.job_manager import job_manager, JobStatus
@@ -14,6 +15,22 @@ from app.services.file_manager import save_upload, get_job_dir
 
 router = APIRouter(prefix="/jobs", tags=["jobs"])
 
+_running_tasks: set[asyncio.Task] = set()
+
+
+def _start_job(coro) -> None:
+    """Schedule a job, keeping a reference so shutdown can cancel it."""
+    task = asyncio.create_task(coro)
+    _running_tasks.add(task)
+    task.add_done_callback(_running_tasks.discard)
+
+
+async def cancel_running_jobs() -> None:
+    """Cancel all in-flight jobs and wait for them to unwind."""
+    for task in _running_tasks:
+        task.cancel()
+    await asyncio.gather(*_running_tasks, return_exceptions=True)
+
 
 def _validate_options(processor, options: dict) -> None:
     """Validate dimension-type options against min/max constraints."""
@@ -71,11 +88,11 @@ async def create_job(
     _validate_options(processor, parsed_options)
 
     data = await file.read()
-    job = job_manager.create(processor_id, file.filename or "upload")
+    job = job_manager.create(processor_id, file.filename or "upload", parsed_options)
     input_path = save_upload(job.id, file.filename or "upload", data)
     output_dir = get_job_dir(job.id)
 
-    asyncio.create_task(_run_job(job.id, processor_id, input_path, output_dir, parsed_options))
+    _start_job(_run_job(job.id, processor_id, input_path, output_dir, parsed_options))
 
     return job.to_dict()
 
@@ -113,7 +130,7 @@ async def create_batch(
     if processor.accepts_multiple_files:
         filenames = [f.filename or "upload" for f in files]
         combined_name = f"{len(files)}_files"
-        job = job_manager.create(processor_id, combined_name)
+        job = job_manager.create(processor_id, combined_name, parsed_options)
         output_dir = get_job_dir(job.id)
 
         input_paths: list[Path] = []
@@ -122,7 +139,7 @@ async def create_batch(
             path = save_upload(job.id, f.filename or "upload", data)
             input_paths.append(path)
 
-        asyncio.create_task(
+        _start_job(
             _run_job(job.id, processor_id, input_paths[0], output_dir, parsed_options, input_paths)
         )
         return {"type": "job", **job.to_dict()}
@@ -131,16 +148,80 @@ async def create_batch(
     job_ids: list[str] = []
     for f in files:
         data = await f.read()
-        job = job_manager.create(processor_id, f.filename or "upload")
+        job = job_manager.create(processor_id, f.filename or "upload", parsed_options)
         input_path = save_upload(job.id, f.filename or "upload", data)
         output_dir = get_job_dir(job.id)
-        asyncio.create_task(_run_job(job.id, processor_id, input_path, output_dir, parsed_options))
+        _start_job(_run_job(job.id, processor_id, input_path, output_dir, parsed_options))
         job_ids.append(job.id)
 
     batch = job_manager.create_batch(processor_id, job_ids)
     return {"type": "batch", **batch.to_dict()}
 
 
+class LocalJobRequest(BaseModel):
+    processor_id: str
+    paths: List[str]
+    options: dict = {}
+
+
+@router.post("/local")
+async def create_local_job(req: LocalJobRequest):
+    """Create jobs for files already on this machine, processed in place.
+
+    Used by the desktop app, which validates the paths first. Inputs are
+    read where they are instead of being copied into ``uploads/``, and
+    ``cleanup_job`` never touches them.
+    """
+    try:
+        processor = get_processor(req.processor_id)
+    except KeyError:
+        raise HTTPException(status_code=400, detail=f"Unknown processor: {req.processor_id}")
+
+    if not req.paths:
+        raise HTTPException(status_code=400, detail="No files given")
+
+    _validate_options(processor, req.options)
+
+    input_paths: list[Path] = []
+    for raw in req.paths:
+        path = Path(raw)
+        if not path.is_absolute() or not path.is_file():
+            raise HTTPException(status_code=400, detail=f"Not a file: {raw}")
+        if path.suffix.lower() not in processor.accepted_extensions:
+            raise HTTPException(
+                status_code=400,
+                detail=f"File type {path.suffix.lower()} not accepted for '{path.name}'. Expected: {processor.accepted_extensions}",
+            )
+        input_paths.append(path)
+
+    # Multi-file processor (or a single file): ONE job
+    if processor.accepts_multiple_files or len(input_paths) == 1:
+        name = input_paths[0].name if len(input_paths) == 1 else f"{len(input_paths)}_files"
+        job = job_manager.create(req.processor_id, name, req.options)
+        output_dir = get_job_dir(job.id)
+        _start_job(
+            _run_job(
+                job.id,
+                req.processor_id,
+                input_paths[0],
+                output_dir,
+                req.options,
+                input_paths if processor.accepts_multiple_files else None,
+            )
+        )
+        return {"type": "job", **job.to_dict()}
+
+    job_ids: list[str] = []
+    for path in input_paths:
+        job = job_manager.create(req.processor_id, path.name, req.options)
+        output_dir = get_job_dir(job.id)
+        _start_job(_run_job(job.id, req.processor_id, path, output_dir, req.options))
+        job_ids.append(job.id)
+
+    batch = job_manager.create_batch(req.processor_id, job_ids)
+    return {"type": "batch", **batch.to_dict()}
+
+
 @router.get("/batch/{batch_id}")
 async def get_batch(batch_id: str):
     batch = job_manager.get_batch(batch_id)
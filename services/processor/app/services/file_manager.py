@@ -1,12 +1,14 @@
+import os
 import shutil
 from pathlib import Path
 
-BASE_DIR = Path(__file__).resolve().parent.parent.parent
+# The desktop app points this at its cache dir so it knows where results live
+BASE_DIR = Path(os.environ.get("VIMIX_DATA_DIR") or Path(__file__).resolve().parent.parent.parent)
 UPLOADS_DIR = BASE_DIR / "uploads"
 JOBS_DIR = BASE_DIR / "jobs"
 
-UPLOADS_DIR.mkdir(exist_ok=True)
-JOBS_DIR.mkdir(exist_ok=True)
+UPLOADS_DIR.mkdir(parents=True, exist_ok=True)
+JOBS_DIR.mkdir(parents=True, exist_ok=True)
 
 
 def save_upload(job_id: str, filename: str, data: bytes) -> Path:
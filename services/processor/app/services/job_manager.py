@@ -20,6 +20,7 @@ class Job:
     id: str
     processor_id: str
     original_filename: str
+    options: dict = field(default_factory=dict)
     status: JobStatus = JobStatus.PENDING
     progress: float = 0
     message: str = ""
@@ -36,10 +37,12 @@ class Job:
             "id": self.id,
             "processor_id": self.processor_id,
             "original_filename": self.original_filename,
+            "options": self.options,
             "status": self.status.value,
             "progress": round(self.progress, 1),
             "message": self.message,
             "result_extension": result_ext,
+            "result_path": self.result_path,
             "error": self.error,
             "created_at": self.created_at,
         }
@@ -66,11 +69,12 @@ class JobManager:
         self._jobs: dict[str, Job] = {}
         self._batches: dict[str, Batch] = {}
 
-    def create(self, processor_id: str, original_filename: str) -> Job:
+    def create(self, processor_id: str, original_filename: str, options: dict | None = None) -> Job:
         job = Job(
             id=uuid.uuid4().hex[:12],
             processor_id=processor_id,
             original_filename=original_filename,
+            options=options or {},
         )
         self._jobs[job.id] = job
         return job
@@ -138,6 +142,10 @@ class JobManager:
                 expired.append(job.id)
         return expired
 
+    def job_ids(self) -> list[str]:
+        """Return the IDs of all known jobs."""
+        return list(self._jobs)
+
     def remove_job(self, job_id: str) -> None:
         """Remove a job and its reference from any batch."""
         self._jobs.pop(job_id, None)